@@ -29,6 +29,7 @@
         fontconfig
         expat
         alsa-lib
+        libopus
       ];
 
       rustToolchain = pkgs.rust-bin.stable.latest.default.override {
@@ -25,6 +25,7 @@ use std::{
 #[derive(Debug, Clone)]
 pub struct SubtitleConfig {
     pub path: PathBuf,
+    pub translation_path: Option<PathBuf>, // the second soft subtitle stream, it's ignored for the embedded subtitle
     pub font_name: String,
     pub font_size: u32,
     pub is_white_font_color: bool,
@@ -37,6 +38,7 @@ impl SubtitleConfig {
     pub fn new(path: impl AsRef<Path>) -> SubtitleConfig {
         SubtitleConfig {
             path: PathBuf::from(path.as_ref()),
+            translation_path: None,
             font_name: "Source Han Sans SC Medium".to_string(),
             font_size: 20,
             is_white_font_color: true,
@@ -46,6 +48,11 @@ impl SubtitleConfig {
         }
     }
 
+    pub fn with_translation_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.translation_path = Some(PathBuf::from(path.as_ref()));
+        self
+    }
+
     pub fn with_font_name(mut self, font_name: &str) -> Self {
         self.font_name = font_name.to_string();
         self
@@ -728,8 +735,16 @@ where
 
         command.args(&["-vf", &filter]).args(&["-c:a", "copy"]);
     } else {
+        command.input(&subtitle_path);
+
+        // only the first subtitle stream is selected without the maps
+        if let Some(translation_path) = subtitle_config.translation_path.as_ref() {
+            command
+                .input(translation_path.to_string_lossy())
+                .args(["-map", "0:v", "-map", "0:a?", "-map", "1:s", "-map", "2:s"]);
+        }
+
         command
-            .input(&subtitle_path)
             .args(&["-c", "copy"])
             .args(&["-c:s", "mov_text"]) // 对于MP4使用mov_text编码
             .args(&["-disposition:s:0", "default"]);
@@ -3,7 +3,7 @@
 use anyhow::{bail, Result};
 use std::path::PathBuf;
 use std::sync::{atomic::AtomicBool, Arc};
-use transcribe::{subtitle, wav, whisper, whisper_lang::WhisperLang, SegmentCallbackData};
+use transcribe::{subtitle, wav, whisper, whisper_lang::WhisperLang};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -41,9 +41,9 @@ async fn main() -> Result<()> {
         config,
         output_audio_path,
         |v: i32| println!("whisper progress: {v}"),
-        move |segment: SegmentCallbackData| {
+        move |segment: whisper::TranscriptionSegment| {
             index += 1;
-            let contents = subtitle::subtitle_to_srt(&segment.into());
+            let contents = subtitle::subtitle_to_srt(&(&segment).into());
             let contents = subtitle::convert_traditional_to_simplified_chinese(&contents);
             println!("---------- {index}--------------");
             println!("{contents}\n");
@@ -0,0 +1,358 @@
+use super::wav::{AudioConfig, AudioData, Resampler, WHISPER_SAMPLE_RATE};
+use anyhow::{anyhow, bail, Context, Result};
+use audiopus::{coder::Decoder as OpusCoder, packet::Packet, Channels, MutSignals, SampleRate};
+use hound::{SampleFormat, WavSpec, WavWriter};
+use log::{debug, warn};
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{CodecParameters, Decoder, DecoderOptions, CODEC_TYPE_NULL, CODEC_TYPE_OPUS},
+    errors::Error as SymphoniaError,
+    formats::{FormatOptions, FormatReader},
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+};
+
+// Opus is demuxed from the ogg files by symphonia, and decoded by libopus
+pub const SUPPORTED_EXTENSIONS: [&str; 7] = ["mp3", "flac", "ogg", "oga", "opus", "m4a", "aac"];
+
+const OPUS_MAX_PACKET_FRAMES: usize = 5760; // 120ms in 48kHz
+
+pub fn is_supported(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            SUPPORTED_EXTENSIONS
+                .iter()
+                .any(|item| item.eq_ignore_ascii_case(ext))
+        })
+}
+
+// Decode the whole file in the original sample rate and channels
+pub fn read_file(path: impl AsRef<Path>) -> Result<AudioData> {
+    let mut stream = AudioStream::open(&path)?;
+    let mut samples = vec![];
+
+    while let Some(packet_samples) = stream.next_samples()? {
+        samples.extend(packet_samples);
+    }
+
+    Ok(AudioData::new(samples, stream.config))
+}
+
+// Decode into a 16kHz mono 16 bit wav file without ffmpeg. The output is
+// removed if it's cancelled or failed, so a partial file is never taken as
+// the converted audio.
+pub fn convert_to_whisper_compatible_audio(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    cancel: Arc<AtomicBool>,
+    progress_cb: impl FnMut(i32) + 'static,
+) -> Result<()> {
+    let result = write_whisper_compatible_audio(&input, &output, cancel, progress_cb);
+    if result.is_err() {
+        _ = fs::remove_file(&output);
+    }
+
+    result
+}
+
+fn write_whisper_compatible_audio(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    cancel: Arc<AtomicBool>,
+    mut progress_cb: impl FnMut(i32) + 'static,
+) -> Result<()> {
+    let mut stream = AudioStream::open(&input)?;
+
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: WHISPER_SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut writer = WavWriter::create(&output, spec)
+        .with_context(|| format!("create {} failed", output.as_ref().display()))?;
+
+    let mut resampler = None;
+
+    while let Some(samples) = stream.next_samples()? {
+        if cancel.load(Ordering::Relaxed) {
+            bail!("cancelled");
+        }
+
+        let mono = AudioData::new(samples, stream.config.clone()).to_mono();
+        let resampler = resampler
+            .get_or_insert_with(|| Resampler::new(mono.config.sample_rate, WHISPER_SAMPLE_RATE));
+        write_samples(&mut writer, resampler.process(&mono.samples))?;
+
+        if let Some(progress) = stream.progress() {
+            progress_cb(progress);
+        }
+    }
+
+    if let Some(resampler) = resampler.as_mut() {
+        write_samples(&mut writer, resampler.flush())?;
+    }
+
+    writer
+        .finalize()
+        .map_err(|e| anyhow!("finalize wav file failed: {e}"))?;
+    progress_cb(100);
+
+    Ok(())
+}
+
+fn write_samples(writer: &mut WavWriter<BufWriter<File>>, samples: Vec<f32>) -> Result<()> {
+    for sample in samples {
+        writer
+            .write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+            .map_err(|e| anyhow!("write wav sample failed: {e}"))?;
+    }
+
+    Ok(())
+}
+
+struct AudioStream {
+    format: Box<dyn FormatReader>,
+    decoder: PacketDecoder,
+    track_id: u32,
+    n_frames: Option<u64>,
+    position: u64, // frame
+    config: AudioConfig,
+}
+
+impl AudioStream {
+    // the format reader, the id and the codec parameters of the audio track
+    fn probe(path: &Path) -> Result<(Box<dyn FormatReader>, u32, CodecParameters)> {
+        let file = File::open(path).with_context(|| format!("open {} failed", path.display()))?;
+        let source = MediaSourceStream::new(Box::new(file), Default::default());
+
+        let mut hint = Hint::new();
+        if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+            hint.with_extension(ext);
+        }
+
+        let probed = symphonia::default::get_probe()
+            .format(
+                &hint,
+                source,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .map_err(|e| anyhow!("unsupported audio format of {}: {e}", path.display()))?;
+
+        let track = probed
+            .format
+            .default_track()
+            .filter(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            .or_else(|| {
+                probed
+                    .format
+                    .tracks()
+                    .iter()
+                    .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            })
+            .map(|track| (track.id, track.codec_params.clone()))
+            .with_context(|| format!("no audio track in {}", path.display()))?;
+
+        Ok((probed.format, track.0, track.1))
+    }
+
+    fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let (format, track_id, codec_params) = Self::probe(path)?;
+
+        let decoder = if codec_params.codec == CODEC_TYPE_OPUS {
+            PacketDecoder::Opus(OpusDecoder::new(&codec_params)?)
+        } else {
+            PacketDecoder::Symphonia(
+                symphonia::default::get_codecs()
+                    .make(&codec_params, &DecoderOptions::default())
+                    .map_err(|e| anyhow!("unsupported audio codec of {}: {e}", path.display()))?,
+            )
+        };
+
+        let config = AudioConfig {
+            sample_rate: codec_params.sample_rate.unwrap_or(WHISPER_SAMPLE_RATE),
+            channels: codec_params
+                .channels
+                .map_or(1, |channels| channels.count() as u16),
+            bit_depth: codec_params.bits_per_sample.unwrap_or(16) as u16,
+        };
+
+        debug!("Decode {} in process: {config:?}", path.display());
+
+        Ok(Self {
+            track_id,
+            n_frames: codec_params.n_frames,
+            position: 0,
+            format,
+            decoder,
+            config,
+        })
+    }
+
+    // The interleaved samples of the next packet. None is the end of the stream.
+    fn next_samples(&mut self) -> Result<Option<Vec<f32>>> {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    return Ok(None);
+                }
+                Err(e) => bail!("read audio packet failed: {e}"),
+            };
+
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            let samples = match &mut self.decoder {
+                PacketDecoder::Symphonia(decoder) => match decoder.decode(&packet) {
+                    Ok(decoded) => {
+                        let spec = *decoded.spec();
+                        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+                        buffer.copy_interleaved_ref(decoded);
+
+                        self.config.sample_rate = spec.rate;
+                        self.config.channels = spec.channels.count() as u16;
+                        buffer.samples().to_vec()
+                    }
+
+                    // skip the corrupted packet
+                    Err(SymphoniaError::DecodeError(e)) => {
+                        warn!("decode audio packet failed: {e}");
+                        continue;
+                    }
+                    Err(e) => bail!("decode audio failed: {e}"),
+                },
+
+                PacketDecoder::Opus(decoder) => match decoder.decode(&packet.data) {
+                    Ok(samples) => samples,
+                    Err(e) => {
+                        warn!("decode opus packet failed: {e}");
+                        continue;
+                    }
+                },
+            };
+
+            self.position = packet.ts() + packet.dur();
+            return Ok(Some(samples));
+        }
+    }
+
+    fn progress(&self) -> Option<i32> {
+        self.n_frames
+            .filter(|n_frames| *n_frames > 0)
+            .map(|n_frames| (self.position.min(n_frames) * 100 / n_frames) as i32)
+    }
+}
+
+enum PacketDecoder {
+    Symphonia(Box<dyn Decoder>),
+    Opus(OpusDecoder),
+}
+
+// The Opus packets are always decoded in 48kHz, and the pre-skip samples are dropped
+struct OpusDecoder {
+    decoder: OpusCoder,
+    channels: usize,
+    skip_frames: usize, // the pre-skip of the encoder
+    buffer: Vec<f32>,
+}
+
+impl OpusDecoder {
+    fn new(codec_params: &CodecParameters) -> Result<Self> {
+        let channels = match codec_params.channels.map_or(1, |channels| channels.count()) {
+            1 => Channels::Mono,
+            2 => Channels::Stereo,
+            n => bail!("Opus of {n} channels is not supported, please convert it with ffmpeg"),
+        };
+
+        let decoder = OpusCoder::new(SampleRate::Hz48000, channels)
+            .map_err(|e| anyhow!("create opus decoder failed: {e}"))?;
+
+        Ok(Self {
+            decoder,
+            channels: channels as usize,
+            skip_frames: codec_params.delay.unwrap_or(0) as usize,
+            buffer: vec![0.0; OPUS_MAX_PACKET_FRAMES * channels as usize],
+        })
+    }
+
+    // the interleaved samples of a packet
+    fn decode(&mut self, data: &[u8]) -> Result<Vec<f32>> {
+        let n_frames = self.decoder.decode_float(
+            Some(Packet::try_from(data)?),
+            MutSignals::try_from(&mut self.buffer)?,
+            false,
+        )?;
+
+        let skip_frames = self.skip_frames.min(n_frames);
+        self.skip_frames -= skip_frames;
+
+        Ok(self.buffer[skip_frames * self.channels..n_frames * self.channels].to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wav;
+
+    #[test]
+    fn test_is_supported() {
+        assert!(is_supported("a.mp3"));
+        assert!(is_supported("/tmp/b.FLAC"));
+        assert!(is_supported("c.m4a"));
+        assert!(!is_supported("d.wav"));
+        assert!(is_supported("e.opus"));
+        assert!(!is_supported("mp3"));
+    }
+
+    // cargo test --features symphonia test_convert_to_whisper_compatible_audio
+    #[test]
+    fn test_convert_to_whisper_compatible_audio() -> Result<()> {
+        let input = "./examples/data/test-440hz.flac";
+        let output = std::env::temp_dir().join("transcribe-decoder-test.wav");
+
+        let audio_data = read_file(input)?;
+        assert_eq!(audio_data.config.sample_rate, 44100);
+        assert_eq!(audio_data.config.channels, 2);
+        assert_eq!(audio_data.frame_count(), 11025);
+
+        convert_to_whisper_compatible_audio(
+            input,
+            &output,
+            Arc::new(AtomicBool::new(false)),
+            |_| {},
+        )?;
+        wav::is_whisper_compatible(&output)?;
+
+        // 0.25 seconds in 16kHz
+        let audio_data = wav::read_file(&output)?;
+        assert!(audio_data.frame_count().abs_diff(4000) <= 16);
+
+        let result = convert_to_whisper_compatible_audio(
+            input,
+            &output,
+            Arc::new(AtomicBool::new(true)),
+            |_| {},
+        );
+        assert!(result.is_err());
+        assert!(!output.exists());
+
+        Ok(())
+    }
+}
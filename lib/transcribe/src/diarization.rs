@@ -0,0 +1,375 @@
+use super::whisper::TranscriptionSegment;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+pub const DEFAULT_MAX_SPEAKERS: usize = 2;
+
+const FRAME_SIZE_MS: usize = 32;
+const BAND_COUNT: usize = 20;
+const MIN_BAND_FREQUENCY: f32 = 100.0;
+const MAX_BAND_FREQUENCY: f32 = 4000.0;
+const SILENCE_RMS: f32 = 0.005;
+const MIN_VOICED_FRAMES: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiarizationMethod {
+    // whisper's tinydiarize, it only works with the `tdrz` models.
+    // The speaker turns are known, but not who is speaking, so the
+    // labels are taken in turn.
+    Tinydiarize,
+
+    // cluster the spectral envelope of the segments, it works with any model
+    Clustering,
+}
+
+pub fn speaker_label(index: usize) -> String {
+    format!("Speaker {}", index + 1)
+}
+
+struct Speaker {
+    centroid: Vec<f32>,
+    count: usize,
+}
+
+// Label the segments with speakers. It keeps the speakers between the calls,
+// so the chunks of a long recording share the same labels.
+pub struct Diarizer {
+    pub method: DiarizationMethod,
+    pub max_speakers: usize,
+    pub distance_threshold: f32, // dB
+
+    current_speaker: usize,
+    speaker_turn: bool,
+    speakers: Vec<Speaker>,
+}
+
+impl Diarizer {
+    pub fn new(method: DiarizationMethod) -> Self {
+        Self {
+            method,
+            max_speakers: DEFAULT_MAX_SPEAKERS,
+            distance_threshold: 3.0,
+            current_speaker: 0,
+            speaker_turn: false,
+            speakers: vec![],
+        }
+    }
+
+    pub fn with_max_speakers(mut self, max_speakers: usize) -> Self {
+        self.max_speakers = max_speakers.max(1);
+        self
+    }
+
+    pub fn with_distance_threshold(mut self, threshold: f32) -> Self {
+        self.distance_threshold = threshold;
+        self
+    }
+
+    // `samples` is the mono audio starting from `offset_ms` of the segment timestamps
+    pub fn diarize(
+        &mut self,
+        samples: &[f32],
+        sample_rate: u32,
+        offset_ms: u64,
+        segments: &mut [TranscriptionSegment],
+    ) {
+        for segment in segments.iter_mut() {
+            let speaker = match self.method {
+                DiarizationMethod::Tinydiarize => {
+                    if self.speaker_turn {
+                        self.current_speaker = (self.current_speaker + 1) % self.max_speakers;
+                    }
+                    self.speaker_turn = segment.speaker_turn;
+                    self.current_speaker
+                }
+                DiarizationMethod::Clustering => {
+                    let ms_to_frames = |ms: u64| {
+                        ((ms.saturating_sub(offset_ms) * sample_rate as u64 / 1000) as usize)
+                            .min(samples.len())
+                    };
+
+                    let (start, end) = (
+                        ms_to_frames(segment.start_time),
+                        ms_to_frames(segment.end_time),
+                    );
+
+                    // too short to tell, it's usually the same speaker
+                    match spectral_envelope(&samples[start..end.max(start)], sample_rate) {
+                        Some(embedding) => self.cluster(embedding),
+                        None => self.current_speaker,
+                    }
+                }
+            };
+
+            self.current_speaker = speaker;
+            segment.speaker = Some(speaker_label(speaker));
+        }
+    }
+
+    fn cluster(&mut self, embedding: Vec<f32>) -> usize {
+        let nearest = self
+            .speakers
+            .iter()
+            .enumerate()
+            .map(|(index, speaker)| (index, distance(&speaker.centroid, &embedding)))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+
+        match nearest {
+            Some((index, distance))
+                if distance <= self.distance_threshold
+                    || self.speakers.len() >= self.max_speakers =>
+            {
+                let speaker = &mut self.speakers[index];
+                speaker.count += 1;
+                for (c, e) in speaker.centroid.iter_mut().zip(embedding.iter()) {
+                    *c += (e - *c) / speaker.count as f32;
+                }
+                index
+            }
+            _ => {
+                self.speakers.push(Speaker {
+                    centroid: embedding,
+                    count: 1,
+                });
+                self.speakers.len() - 1
+            }
+        }
+    }
+}
+
+// root mean square of the differences
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    let sum = a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum::<f32>();
+    (sum / a.len().max(1) as f32).sqrt()
+}
+
+// The average log energies of the mel bands of the voiced frames. The mean of each
+// frame is removed, so it describes the timbre rather than the loudness.
+fn spectral_envelope(samples: &[f32], sample_rate: u32) -> Option<Vec<f32>> {
+    let frame_size = (sample_rate as usize * FRAME_SIZE_MS / 1000).next_power_of_two();
+    let window = (0..frame_size)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / frame_size as f32).cos())
+        .collect::<Vec<_>>();
+
+    let hz_to_mel = |hz: f32| 2595.0 * (1.0 + hz / 700.0).log10();
+    let mel_to_hz = |mel: f32| 700.0 * (10f32.powf(mel / 2595.0) - 1.0);
+    let (min_mel, max_mel) = (
+        hz_to_mel(MIN_BAND_FREQUENCY),
+        hz_to_mel(MAX_BAND_FREQUENCY.min(sample_rate as f32 / 2.0)),
+    );
+    let band_edges = (0..=BAND_COUNT)
+        .map(|i| {
+            let hz = mel_to_hz(min_mel + (max_mel - min_mel) * i as f32 / BAND_COUNT as f32);
+            ((hz * frame_size as f32 / sample_rate as f32) as usize).min(frame_size / 2)
+        })
+        .collect::<Vec<_>>();
+
+    let mut envelope = vec![0.0; BAND_COUNT];
+    let mut voiced_frames = 0;
+    let (mut re, mut im) = (vec![0.0; frame_size], vec![0.0; frame_size]);
+
+    for frame in samples.chunks_exact(frame_size) {
+        let rms = (frame.iter().map(|s| s * s).sum::<f32>() / frame_size as f32).sqrt();
+        if rms < SILENCE_RMS {
+            continue;
+        }
+
+        for i in 0..frame_size {
+            re[i] = frame[i] * window[i];
+            im[i] = 0.0;
+        }
+        fft(&mut re, &mut im);
+
+        let bands = band_edges
+            .windows(2)
+            .map(|edge| {
+                let energy = (edge[0]..edge[1].max(edge[0] + 1))
+                    .map(|k| re[k] * re[k] + im[k] * im[k])
+                    .sum::<f32>();
+                10.0 * (energy + 1e-10).log10()
+            })
+            .collect::<Vec<_>>();
+
+        let mean = bands.iter().sum::<f32>() / BAND_COUNT as f32;
+        for (e, b) in envelope.iter_mut().zip(bands) {
+            *e += b - mean;
+        }
+        voiced_frames += 1;
+    }
+
+    if voiced_frames < MIN_VOICED_FRAMES {
+        return None;
+    }
+
+    Some(
+        envelope
+            .into_iter()
+            .map(|e| e / voiced_frames as f32)
+            .collect(),
+    )
+}
+
+// in-place radix-2 fft, the length should be a power of two
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (w_re, w_im) = ((angle * k as f32).cos(), (angle * k as f32).sin());
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 16000;
+
+    fn segment(start_time: u64, end_time: u64, speaker_turn: bool) -> TranscriptionSegment {
+        TranscriptionSegment {
+            index: 0,
+            start_time,
+            end_time,
+            text: "hello".to_string(),
+            confidence: 1.0,
+            words: vec![],
+            speaker: None,
+            speaker_turn,
+            language: None,
+            hallucination: vec![],
+        }
+    }
+
+    // a harmonic tone, the timbre depends on the weights of the harmonics
+    fn voice(pitch: f32, harmonics: &[f32], seconds: usize) -> Vec<f32> {
+        (0..SAMPLE_RATE as usize * seconds)
+            .map(|i| {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                harmonics
+                    .iter()
+                    .enumerate()
+                    .map(|(n, w)| w * (2.0 * PI * pitch * (n + 1) as f32 * t).sin())
+                    .sum::<f32>()
+                    * 0.1
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_diarize_speaker_turns() {
+        let mut segments = vec![
+            segment(0, 1000, false),
+            segment(1000, 2000, true),
+            segment(2000, 3000, false),
+            segment(3000, 4000, true),
+            segment(4000, 5000, false),
+        ];
+
+        Diarizer::new(DiarizationMethod::Tinydiarize).diarize(&[], SAMPLE_RATE, 0, &mut segments);
+
+        let speakers = segments
+            .iter()
+            .map(|item| item.speaker.clone().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            speakers,
+            vec![
+                "Speaker 1",
+                "Speaker 1",
+                "Speaker 2",
+                "Speaker 2",
+                "Speaker 1"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diarize_clustering() {
+        let low = voice(120.0, &[1.0, 0.8, 0.6, 0.4, 0.2], 2);
+        let high = voice(240.0, &[0.2, 0.2, 1.0, 0.1, 0.6], 2);
+        let samples = [low.clone(), high.clone(), low, high].concat();
+
+        let mut segments = vec![
+            segment(10000, 12000, false),
+            segment(12000, 14000, false),
+            segment(14000, 16000, false),
+            segment(16000, 18000, false),
+            segment(18000, 18010, false),
+        ];
+
+        Diarizer::new(DiarizationMethod::Clustering).diarize(
+            &samples,
+            SAMPLE_RATE,
+            10000,
+            &mut segments,
+        );
+
+        let speakers = segments
+            .iter()
+            .map(|item| item.speaker.clone().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            speakers,
+            vec![
+                "Speaker 1",
+                "Speaker 2",
+                "Speaker 1",
+                "Speaker 2",
+                "Speaker 2" // too short, it follows the previous one
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diarize_max_speakers() {
+        let samples = [
+            voice(120.0, &[1.0, 0.8, 0.6], 1),
+            voice(240.0, &[0.2, 1.0, 0.1], 1),
+            voice(480.0, &[1.0, 0.0, 1.0], 1),
+        ]
+        .concat();
+
+        let mut segments = vec![
+            segment(0, 1000, false),
+            segment(1000, 2000, false),
+            segment(2000, 3000, false),
+        ];
+
+        Diarizer::new(DiarizationMethod::Clustering)
+            .with_max_speakers(2)
+            .diarize(&samples, SAMPLE_RATE, 0, &mut segments);
+
+        assert!(segments
+            .iter()
+            .all(|item| item.speaker.as_deref() != Some("Speaker 3")));
+    }
+}
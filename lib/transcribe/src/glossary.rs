@@ -0,0 +1,293 @@
+use super::whisper::TranscriptionSegment;
+
+const DEFAULT_SIMILARITY_THRESHOLD: f32 = 0.8;
+const MIN_FUZZY_TERM_LENGTH: usize = 6; // characters, the shorter terms are only matched in case-insensitive
+const MAX_EXTRA_WORDS: usize = 1; // "Kubernetes" may be transcribed as "Cuber Netes"
+
+// The domain terms, e.g. the product names, the people and the jargon.
+// They are in the prompt of whisper, and fix the misspellings of the transcribed text.
+#[derive(Debug, Clone)]
+pub struct Glossary {
+    pub terms: Vec<String>,
+    pub similarity_threshold: f32, // (0.0-1.0)
+}
+
+impl Glossary {
+    pub fn new<S: AsRef<str>>(terms: impl IntoIterator<Item = S>) -> Self {
+        let mut items: Vec<String> = vec![];
+        for term in terms {
+            let term = term.as_ref().trim();
+            if !term.is_empty() && !items.iter().any(|item| item == term) {
+                items.push(term.to_string());
+            }
+        }
+
+        Self {
+            terms: items,
+            similarity_threshold: DEFAULT_SIMILARITY_THRESHOLD,
+        }
+    }
+
+    pub fn with_similarity_threshold(mut self, threshold: f32) -> Self {
+        self.similarity_threshold = threshold.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    // whisper follows the spelling of the words in the prompt
+    pub fn prompt(&self) -> Option<String> {
+        if self.terms.is_empty() {
+            return None;
+        }
+
+        Some(format!("Glossary: {}.", self.terms.join(", ")))
+    }
+
+    // Replace the words which look like a term with the term, and the punctuations
+    // around the words are kept. The words are separated by spaces, and each character
+    // of the scripts without spaces is a word, e.g. Chinese, so the terms are also found
+    // in the Chinese text mixed with the English words without spaces.
+    pub fn apply(&self, text: &str) -> String {
+        if self.terms.is_empty() {
+            return text.to_string();
+        }
+
+        let words = split_words(text);
+        let mut candidates = vec![];
+        for index in 0..words.len() {
+            if normalize(words[index].1).is_empty() {
+                continue;
+            }
+
+            for term in self.terms.iter() {
+                if let Some((count, similarity)) = self.match_term(term, &words[index..]) {
+                    candidates.push((index, count, term.as_str(), similarity));
+                }
+            }
+        }
+
+        // the most similar ones are replaced first, so the words around a term are kept
+        candidates.sort_by(|a, b| b.3.total_cmp(&a.3).then(a.1.cmp(&b.1)).then(a.0.cmp(&b.0)));
+
+        let mut is_replaced = vec![false; words.len()];
+        let mut replacements = vec![];
+        for (index, count, term, _) in candidates {
+            if is_replaced[index..index + count].contains(&true) {
+                continue;
+            }
+
+            is_replaced[index..index + count].fill(true);
+            replacements.push((index, count, term));
+        }
+        replacements.sort_by_key(|(index, _, _)| *index);
+
+        let mut output = String::new();
+        let mut pos = 0;
+        for (index, count, term) in replacements {
+            let (first_start, first_word) = words[index];
+            let (last_start, last_word) = words[index + count - 1];
+            let (prefix, _) = split_punctuation(first_word);
+            let (_, suffix) = split_punctuation(last_word);
+
+            output.push_str(&text[pos..first_start + prefix.len()]);
+            output.push_str(term);
+            pos = last_start + last_word.len() - suffix.len();
+        }
+        output.push_str(&text[pos..]);
+
+        output
+    }
+
+    pub fn apply_to_segments(&self, segments: &mut [TranscriptionSegment]) {
+        for segment in segments.iter_mut() {
+            segment.text = self.apply(&segment.text);
+        }
+    }
+
+    // The number of the words the term replaces and the similarity
+    fn match_term(&self, term: &str, words: &[(usize, &str)]) -> Option<(usize, f32)> {
+        let term_key = normalize(term);
+        let term_words = split_words(term).len().max(1);
+        if term_key.is_empty() {
+            return None;
+        }
+
+        let mut best: Option<(usize, f32)> = None;
+        for count in term_words.saturating_sub(MAX_EXTRA_WORDS).max(1)
+            ..=(term_words + MAX_EXTRA_WORDS).min(words.len())
+        {
+            // the punctuations after the words are not replaced
+            if normalize(words[count - 1].1).is_empty() {
+                continue;
+            }
+
+            let key = words[..count]
+                .iter()
+                .map(|(_, word)| normalize(word))
+                .collect::<String>();
+
+            let similarity = if key == term_key {
+                1.0
+            } else if term_key.chars().count() < MIN_FUZZY_TERM_LENGTH {
+                continue;
+            } else {
+                similarity(&key, &term_key)
+            };
+
+            if similarity >= self.similarity_threshold
+                && best.is_none_or(|(_, best_similarity)| similarity > best_similarity)
+            {
+                best = Some((count, similarity));
+            }
+        }
+
+        best
+    }
+}
+
+// The words and their byte offsets. The words are separated by spaces,
+// and each character of the scripts without spaces is a word.
+fn split_words(text: &str) -> Vec<(usize, &str)> {
+    let mut words = vec![];
+    let mut start = None;
+
+    for (index, c) in text.char_indices() {
+        if c.is_whitespace() || is_unsegmented(c) {
+            if let Some(start) = start.take() {
+                words.push((start, &text[start..index]));
+            }
+
+            if is_unsegmented(c) {
+                words.push((index, &text[index..index + c.len_utf8()]));
+            }
+        } else if start.is_none() {
+            start = Some(index);
+        }
+    }
+
+    if let Some(start) = start {
+        words.push((start, &text[start..]));
+    }
+
+    words
+}
+
+// the Chinese characters and the Japanese kana
+fn is_unsegmented(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}' // Hiragana and Katakana
+        | '\u{3400}'..='\u{4DBF}' // CJK Unified Ideographs Extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK Unified Ideographs
+        | '\u{F900}'..='\u{FAFF}' // CJK Compatibility Ideographs
+        | '\u{20000}'..='\u{2FA1F}' // CJK Unified Ideographs Extension B to F
+    )
+}
+
+// the lowercase letters and digits
+fn normalize(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+// the leading and trailing punctuations of a word
+fn split_punctuation(word: &str) -> (&str, &str) {
+    let start = word
+        .find(|c: char| c.is_alphanumeric())
+        .unwrap_or(word.len());
+    let end = word
+        .rfind(|c: char| c.is_alphanumeric())
+        .map_or(start, |index| {
+            index + word[index..].chars().next().map_or(0, char::len_utf8)
+        });
+
+    (&word[..start], &word[end.max(start)..])
+}
+
+// 1.0 is the same, and 0.0 is totally different
+fn similarity(a: &str, b: &str) -> f32 {
+    let (a, b) = (a.chars().collect::<Vec<_>>(), b.chars().collect::<Vec<_>>());
+    let max_len = a.len().max(b.len());
+    if max_len == 0 {
+        return 1.0;
+    }
+
+    1.0 - levenshtein(&a, &b) as f32 / max_len as f32
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            current[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut current);
+    }
+
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glossary_prompt() {
+        assert_eq!(Glossary::new(Vec::<String>::new()).prompt(), None);
+        assert_eq!(
+            Glossary::new(["Slint", " whisper.cpp ", "", "Slint"]).prompt(),
+            Some("Glossary: Slint, whisper.cpp.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_glossary_apply() {
+        let glossary = Glossary::new(["Kubernetes", "Slint", "Heng30", "PostgreSQL"]);
+
+        assert_eq!(
+            glossary.apply("We deploy it on kubernetis, and Cuber Netes."),
+            "We deploy it on Kubernetes, and Kubernetes."
+        );
+        assert_eq!(
+            glossary.apply("The UI is written in slint by heng30."),
+            "The UI is written in Slint by Heng30."
+        );
+        assert_eq!(
+            glossary.apply("Save it in Postgre SQL"),
+            "Save it in PostgreSQL"
+        );
+
+        // the short terms are not fuzzy matched
+        assert_eq!(
+            glossary.apply("a saint and a sprint"),
+            "a saint and a sprint"
+        );
+        assert_eq!(glossary.apply("nothing to fix"), "nothing to fix");
+    }
+
+    #[test]
+    fn test_glossary_apply_unsegmented() {
+        let glossary = Glossary::new(["Kubernetes", "Slint", "深度求索人工智能"]);
+
+        assert_eq!(
+            glossary.apply("我们把它部署在kubernetis上。"),
+            "我们把它部署在Kubernetes上。"
+        );
+        assert_eq!(glossary.apply("界面是用slint写的"), "界面是用Slint写的");
+        assert_eq!(glossary.apply("用Cuber Netes部署"), "用Kubernetes部署");
+        assert_eq!(
+            glossary.apply("这是深度球索人工智能的模型，"),
+            "这是深度求索人工智能的模型，"
+        );
+        assert_eq!(glossary.apply("用深度求索人工智能"), "用深度求索人工智能");
+        assert_eq!(glossary.apply("没有需要修改的"), "没有需要修改的");
+    }
+}
@@ -0,0 +1,325 @@
+use super::{vad::EnergyVAD, whisper::TranscriptionSegment};
+use flate2::{write::ZlibEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use unicode_segmentation::UnicodeSegmentation;
+
+const MAX_NGRAM_SIZE: usize = 8; // words
+const MIN_REPEATED_WORDS: usize = 6; // "no no no" is fine, "thank you thank you thank you" isn't
+const MIN_COMPRESSION_TEXT_LENGTH: usize = 32; // bytes, the ratio of a short text is meaningless
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HallucinationReason {
+    Repetition,
+    HighCompressionRatio,
+    LowConfidence,
+    NoSpeech,
+}
+
+impl HallucinationReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HallucinationReason::Repetition => "repetition",
+            HallucinationReason::HighCompressionRatio => "compression-ratio",
+            HallucinationReason::LowConfidence => "low-confidence",
+            HallucinationReason::NoSpeech => "no-speech",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HallucinationAction {
+    Mark,
+    Remove,
+}
+
+// Flag the segments which whisper likely made up, e.g. the repeated lines
+// or the text over the silence
+#[derive(Debug, Clone)]
+pub struct HallucinationDetector {
+    pub min_repeats: usize, // of an n-gram in a segment, or a line in the consecutive segments
+    pub compression_ratio_threshold: f32, // the same as whisper
+    pub min_confidence: f32,
+    pub speech_threshold: f32, // RMS of the VAD
+}
+
+impl Default for HallucinationDetector {
+    fn default() -> Self {
+        Self {
+            min_repeats: 3,
+            compression_ratio_threshold: 2.4,
+            min_confidence: 0.4,
+            speech_threshold: 0.01,
+        }
+    }
+}
+
+impl HallucinationDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_min_repeats(mut self, min_repeats: usize) -> Self {
+        self.min_repeats = min_repeats.max(2);
+        self
+    }
+
+    pub fn with_compression_ratio_threshold(mut self, threshold: f32) -> Self {
+        self.compression_ratio_threshold = threshold;
+        self
+    }
+
+    pub fn with_min_confidence(mut self, min_confidence: f32) -> Self {
+        self.min_confidence = min_confidence;
+        self
+    }
+
+    pub fn with_speech_threshold(mut self, threshold: f32) -> Self {
+        self.speech_threshold = threshold;
+        self
+    }
+
+    // The reasons of each segment, and empty is not flagged. `audio` is the mono
+    // samples and the sample rate, starting from 0ms of the segment timestamps.
+    pub fn detect(
+        &self,
+        segments: &[TranscriptionSegment],
+        audio: Option<(&[f32], u32)>,
+    ) -> Vec<Vec<HallucinationReason>> {
+        segments
+            .iter()
+            .enumerate()
+            .map(|(index, segment)| {
+                let mut reasons = vec![];
+
+                // the first line of the repeated lines is kept
+                let text = normalize(&segment.text);
+                let is_repeated_line = index > 0
+                    && !text.is_empty()
+                    && text == normalize(&segments[index - 1].text)
+                    && is_repeated_run(segments, index, self.min_repeats);
+
+                if is_repeated_line || has_repeated_ngram(&segment.text, self.min_repeats) {
+                    reasons.push(HallucinationReason::Repetition);
+                }
+
+                if compression_ratio(&segment.text)
+                    .is_some_and(|ratio| ratio > self.compression_ratio_threshold)
+                {
+                    reasons.push(HallucinationReason::HighCompressionRatio);
+                }
+
+                if segment.confidence < self.min_confidence {
+                    reasons.push(HallucinationReason::LowConfidence);
+                }
+
+                if audio.is_some_and(|(samples, sample_rate)| {
+                    !self.has_speech(samples, sample_rate, segment)
+                }) {
+                    reasons.push(HallucinationReason::NoSpeech);
+                }
+
+                reasons
+            })
+            .collect()
+    }
+
+    // Return the number of the flagged segments
+    pub fn apply(
+        &self,
+        segments: &mut Vec<TranscriptionSegment>,
+        audio: Option<(&[f32], u32)>,
+        action: HallucinationAction,
+    ) -> usize {
+        let flags = self.detect(segments, audio);
+        let count = flags.iter().filter(|reasons| !reasons.is_empty()).count();
+
+        match action {
+            HallucinationAction::Mark => {
+                for (segment, reasons) in segments.iter_mut().zip(flags) {
+                    segment.hallucination = reasons;
+                }
+            }
+            HallucinationAction::Remove => {
+                let mut flags = flags.into_iter();
+                segments.retain(|_| flags.next().is_none_or(|reasons| reasons.is_empty()));
+                for (index, segment) in segments.iter_mut().enumerate() {
+                    segment.index = index as i32 + 1;
+                }
+            }
+        }
+
+        count
+    }
+
+    fn has_speech(
+        &self,
+        samples: &[f32],
+        sample_rate: u32,
+        segment: &TranscriptionSegment,
+    ) -> bool {
+        let ms_to_index = |ms: u64| ((ms * sample_rate as u64 / 1000) as usize).min(samples.len());
+        let (start, end) = (
+            ms_to_index(segment.start_time),
+            ms_to_index(segment.end_time),
+        );
+
+        // out of the audio, it can't tell
+        if start >= end {
+            return true;
+        }
+
+        let vad = EnergyVAD::new(sample_rate).with_threshold(self.speech_threshold);
+        let frame_size = (sample_rate as u64 * vad.frame_size_ms / 1000).max(1) as usize;
+
+        samples[start..end]
+            .chunks(frame_size)
+            .any(|frame| vad.contain_speech(frame))
+    }
+}
+
+// the lowercase words without punctuations
+fn normalize(text: &str) -> String {
+    text.unicode_words()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// the segment is in a run of at least `min_repeats` identical lines
+fn is_repeated_run(segments: &[TranscriptionSegment], index: usize, min_repeats: usize) -> bool {
+    let text = normalize(&segments[index].text);
+    let following = segments[index + 1..]
+        .iter()
+        .take_while(|segment| normalize(&segment.text) == text)
+        .count();
+    let preceding = segments[..index]
+        .iter()
+        .rev()
+        .take_while(|segment| normalize(&segment.text) == text)
+        .count();
+
+    preceding + 1 + following >= min_repeats
+}
+
+// an n-gram repeats at least `min_repeats` times in a row
+fn has_repeated_ngram(text: &str, min_repeats: usize) -> bool {
+    let words = text
+        .unicode_words()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>();
+
+    for size in 1..=MAX_NGRAM_SIZE.min(words.len() / min_repeats.max(1)) {
+        let min_repeats = min_repeats.max(MIN_REPEATED_WORDS.div_ceil(size));
+
+        for start in 0..words.len() {
+            let mut repeats = 1;
+            while start + (repeats + 1) * size <= words.len()
+                && words[start..start + size]
+                    == words[start + repeats * size..start + (repeats + 1) * size]
+            {
+                repeats += 1;
+            }
+
+            if repeats >= min_repeats {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+// The size of the text divided by its zlib compressed size. The repeated text is
+// compressed well, so the ratio is high. None is the text too short to tell.
+pub fn compression_ratio(text: &str) -> Option<f32> {
+    let text = text.trim();
+    if text.len() < MIN_COMPRESSION_TEXT_LENGTH {
+        return None;
+    }
+
+    let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+    encoder.write_all(text.as_bytes()).ok()?;
+    let compressed = encoder.finish().ok()?;
+
+    Some(text.len() as f32 / compressed.len().max(1) as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start_time: u64, end_time: u64, text: &str) -> TranscriptionSegment {
+        TranscriptionSegment {
+            index: 0,
+            start_time,
+            end_time,
+            text: text.to_string(),
+            confidence: 0.9,
+            words: vec![],
+            speaker: None,
+            language: None,
+            speaker_turn: false,
+            hallucination: vec![],
+        }
+    }
+
+    #[test]
+    fn test_repeated_ngram() {
+        assert!(has_repeated_ngram("Thank you. Thank you. Thank you.", 3));
+        assert!(has_repeated_ngram(
+            "I said that the the the the the the end",
+            3
+        ));
+        assert!(!has_repeated_ngram("No, no, no. I don't think so.", 3));
+        assert!(!has_repeated_ngram("Thank you. Thank you.", 3));
+        assert!(!has_repeated_ngram("", 3));
+    }
+
+    #[test]
+    fn test_compression_ratio() {
+        assert_eq!(compression_ratio("too short"), None);
+
+        let repeated = "we are going to the park. ".repeat(10);
+        assert!(compression_ratio(&repeated).unwrap() > 2.4);
+
+        let normal = "The quick brown fox jumps over the lazy dog near the river bank.";
+        assert!(compression_ratio(normal).unwrap() < 2.4);
+    }
+
+    #[test]
+    fn test_detect() {
+        let mut segments = vec![
+            segment(0, 1000, "Hello everyone."),
+            segment(1000, 2000, "Thank you."),
+            segment(2000, 3000, "thank you"),
+            segment(3000, 4000, "Thank you!"),
+            segment(4000, 5000, "Bye."),
+            segment(5000, 6000, "Subscribe to my channel."),
+        ];
+        segments[4].confidence = 0.1;
+
+        // speech in the first 5 seconds, then silence
+        let sample_rate = 1000;
+        let mut samples = vec![0.5; 6000];
+        samples[5000..].fill(0.0);
+
+        let flags = HallucinationDetector::new().detect(&segments, Some((&samples, sample_rate)));
+        assert!(flags[0].is_empty());
+        assert!(flags[1].is_empty());
+        assert_eq!(flags[2], vec![HallucinationReason::Repetition]);
+        assert_eq!(flags[3], vec![HallucinationReason::Repetition]);
+        assert_eq!(flags[4], vec![HallucinationReason::LowConfidence]);
+        assert_eq!(flags[5], vec![HallucinationReason::NoSpeech]);
+
+        let count = HallucinationDetector::new().apply(
+            &mut segments,
+            Some((&samples, sample_rate)),
+            HallucinationAction::Remove,
+        );
+        assert_eq!(count, 4);
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[1].text, "Thank you.");
+        assert_eq!(segments[1].index, 2);
+    }
+}
@@ -0,0 +1,177 @@
+use anyhow::{anyhow, bail, Result};
+use std::{ffi::c_void, os::raw::c_int, slice};
+use whisper_rs::{
+    FullParams, WhisperContext, WhisperSysContext, WhisperSysState, WhisperTokenData,
+    WhisperTokenId,
+};
+
+const MAX_HOTWORD_TOKENS: usize = 32;
+pub const DEFAULT_HOTWORD_BIAS: f32 = 2.0;
+pub const MAX_HOTWORD_BIAS: f32 = 10.0; // the larger bias makes whisper repeat the hotwords
+
+// Add the bias to the logits of the hotword tokens in decoding. The first token
+// of a hotword is always boosted, and the next token is boosted when the decoded
+// tokens end with a part of the hotword.
+#[derive(Debug, Clone, Default)]
+pub struct HotwordBias {
+    n_vocab: usize,
+    sequences: Vec<(Vec<WhisperTokenId>, f32)>,
+}
+
+impl HotwordBias {
+    // A hotword is tokenized with and without the leading space,
+    // because it could be at the beginning or in the middle of a sentence
+    pub fn new(context: &WhisperContext, hotwords: &[(String, f32)]) -> Result<Option<Self>> {
+        let mut sequences = vec![];
+
+        for (word, bias) in hotwords.iter() {
+            let word = word.trim();
+            if word.is_empty() || *bias == 0.0 {
+                continue;
+            }
+
+            for text in [format!(" {word}"), word.to_string()] {
+                let tokens = context
+                    .tokenize(&text, MAX_HOTWORD_TOKENS)
+                    .map_err(|e| anyhow!("tokenize hotword {word} failed: {e}"))?;
+
+                if !tokens.is_empty() {
+                    sequences.push((tokens, *bias));
+                }
+            }
+        }
+
+        Ok(Self::from_tokens(
+            context.n_vocab().max(0) as usize,
+            sequences,
+        ))
+    }
+
+    pub fn from_tokens(n_vocab: usize, sequences: Vec<(Vec<WhisperTokenId>, f32)>) -> Option<Self> {
+        let sequences = sequences
+            .into_iter()
+            .filter(|(tokens, _)| {
+                !tokens.is_empty()
+                    && tokens
+                        .iter()
+                        .all(|token| *token >= 0 && (*token as usize) < n_vocab)
+            })
+            .collect::<Vec<_>>();
+
+        if sequences.is_empty() {
+            None
+        } else {
+            Some(Self { n_vocab, sequences })
+        }
+    }
+
+    // The bias of a token is the largest one of the hotwords
+    pub fn apply(&self, tokens: &[WhisperTokenId], logits: &mut [f32]) {
+        let mut biases: Vec<(WhisperTokenId, f32)> = vec![];
+        let mut add_bias = |token: WhisperTokenId, bias: f32| match biases
+            .iter_mut()
+            .find(|(item, _)| *item == token)
+        {
+            Some((_, value)) => *value = value.max(bias),
+            None => biases.push((token, bias)),
+        };
+
+        for (sequence, bias) in self.sequences.iter() {
+            add_bias(sequence[0], *bias);
+
+            for matched in 1..sequence.len() {
+                if tokens.ends_with(&sequence[..matched]) {
+                    add_bias(sequence[matched], *bias);
+                }
+            }
+        }
+
+        for (token, bias) in biases {
+            if let Some(logit) = logits.get_mut(token as usize) {
+                *logit += bias;
+            }
+        }
+    }
+
+    // The bias must outlive the params
+    pub fn set_logits_filter(&self, params: &mut FullParams) {
+        unsafe {
+            params.set_filter_logits_callback(Some(logits_filter_callback));
+            params.set_filter_logits_callback_user_data(self as *const Self as *mut c_void);
+        }
+    }
+}
+
+pub fn validate(hotwords: &[(String, f32)]) -> Result<()> {
+    for (word, bias) in hotwords.iter() {
+        if !bias.is_finite() || bias.abs() > MAX_HOTWORD_BIAS {
+            bail!("the bias of hotword {word} should between -{MAX_HOTWORD_BIAS} and {MAX_HOTWORD_BIAS}");
+        }
+    }
+
+    Ok(())
+}
+
+unsafe extern "C" fn logits_filter_callback(
+    _ctx: *mut WhisperSysContext,
+    _state: *mut WhisperSysState,
+    tokens: *const WhisperTokenData,
+    n_tokens: c_int,
+    logits: *mut f32,
+    user_data: *mut c_void,
+) {
+    if user_data.is_null() || logits.is_null() {
+        return;
+    }
+
+    let bias = unsafe { &*(user_data as *const HotwordBias) };
+    let logits = unsafe { slice::from_raw_parts_mut(logits, bias.n_vocab) };
+    let tokens = if tokens.is_null() || n_tokens <= 0 {
+        vec![]
+    } else {
+        unsafe { slice::from_raw_parts(tokens, n_tokens as usize) }
+            .iter()
+            .map(|token| token.id)
+            .collect()
+    };
+
+    bias.apply(&tokens, logits);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hotword_bias() {
+        assert!(HotwordBias::from_tokens(10, vec![(vec![], 1.0), (vec![20], 1.0)]).is_none());
+
+        let bias =
+            HotwordBias::from_tokens(10, vec![(vec![1, 2, 3], 2.0), (vec![1, 4], 3.0)]).unwrap();
+
+        // only the first tokens
+        let mut logits = vec![0.0; 10];
+        bias.apply(&[7, 8], &mut logits);
+        assert_eq!(logits[1], 3.0);
+        assert_eq!(logits[2], 0.0);
+
+        // the decoded tokens end with [1, 2]
+        let mut logits = vec![0.0; 10];
+        bias.apply(&[7, 1, 2], &mut logits);
+        assert_eq!(logits[1], 3.0);
+        assert_eq!(logits[3], 2.0);
+        assert_eq!(logits[4], 0.0);
+
+        let mut logits = vec![0.0; 10];
+        bias.apply(&[1], &mut logits);
+        assert_eq!(logits[2], 2.0);
+        assert_eq!(logits[4], 3.0);
+    }
+
+    #[test]
+    fn test_validate_hotwords() {
+        assert!(validate(&[("Slint".to_string(), 2.0)]).is_ok());
+        assert!(validate(&[("Slint".to_string(), 100.0)]).is_err());
+        assert!(validate(&[("Slint".to_string(), f32::NAN)]).is_err());
+    }
+}
@@ -1,3 +1,10 @@
+#[cfg(feature = "symphonia")]
+pub mod decoder;
+pub mod diarization;
+pub mod glossary;
+pub mod hallucination;
+pub mod hotword;
+pub mod model_cache;
 pub mod subtitle;
 pub mod vad;
 pub mod wav;
@@ -5,8 +12,6 @@ pub mod whisper;
 pub mod whisper_lang;
 pub mod whisper_model_downloader;
 
-pub use whisper_rs::SegmentCallbackData;
-
 #[derive(Debug, Clone)]
 pub enum ProgressStatus {
     Finished,
@@ -0,0 +1,238 @@
+use anyhow::{anyhow, Result};
+use log::debug;
+use once_cell::sync::Lazy;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+use whisper_rs::{WhisperContext, WhisperContextParameters};
+
+pub const DEFAULT_MEMORY_BUDGET: u64 = 4 * 1024 * 1024 * 1024; // bytes
+
+static WHISPER_MODELS: Lazy<Mutex<ModelCache<WhisperContext>>> =
+    Lazy::new(|| Mutex::new(ModelCache::new(DEFAULT_MEMORY_BUDGET)));
+
+// A re-downloaded model file has another modified time, so it's loaded again
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ModelKey {
+    pub model_path: PathBuf,
+    pub modified: Option<SystemTime>,
+    pub use_gpu: bool,
+    pub flash_attn: bool,
+    pub gpu_device: i32,
+    pub dtw: String, // the debug string, DtwMode isn't comparable
+}
+
+impl ModelKey {
+    pub fn new(model_path: impl AsRef<Path>, params: &WhisperContextParameters) -> Self {
+        let model_path = model_path.as_ref();
+
+        Self {
+            model_path: fs::canonicalize(model_path).unwrap_or(model_path.to_path_buf()),
+            modified: fs::metadata(model_path)
+                .and_then(|meta| meta.modified())
+                .ok(),
+            use_gpu: params.use_gpu,
+            flash_attn: params.flash_attn,
+            gpu_device: params.gpu_device,
+            dtw: format!("{:?}", params.dtw_parameters.mode),
+        }
+    }
+}
+
+struct CacheEntry<T> {
+    key: ModelKey,
+    size: u64,
+    model: Arc<T>,
+}
+
+// The least recently used models are evicted when the total size is over the
+// memory budget. The last used model is always kept even if it's over the budget.
+pub struct ModelCache<T> {
+    pub memory_budget: u64,
+    entries: Vec<CacheEntry<T>>, // the last one is the most recently used
+}
+
+impl<T> ModelCache<T> {
+    pub fn new(memory_budget: u64) -> Self {
+        Self {
+            memory_budget,
+            entries: vec![],
+        }
+    }
+
+    pub fn get_or_load(
+        &mut self,
+        key: ModelKey,
+        size: u64,
+        load: impl FnOnce() -> Result<T>,
+    ) -> Result<Arc<T>> {
+        if let Some(index) = self.entries.iter().position(|entry| entry.key == key) {
+            let entry = self.entries.remove(index);
+            let model = entry.model.clone();
+            self.entries.push(entry);
+            return Ok(model);
+        }
+
+        let model = Arc::new(load()?);
+        self.entries.push(CacheEntry {
+            key,
+            size,
+            model: model.clone(),
+        });
+        self.evict();
+
+        Ok(model)
+    }
+
+    pub fn set_memory_budget(&mut self, memory_budget: u64) {
+        self.memory_budget = memory_budget;
+        self.evict();
+    }
+
+    pub fn contains(&self, key: &ModelKey) -> bool {
+        self.entries.iter().any(|entry| &entry.key == key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn memory_usage(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.size).sum()
+    }
+
+    // remove the model loaded with any context parameters
+    pub fn remove(&mut self, model_path: impl AsRef<Path>) {
+        let model_path = model_path.as_ref();
+        let model_path = fs::canonicalize(model_path).unwrap_or(model_path.to_path_buf());
+        self.entries
+            .retain(|entry| entry.key.model_path != model_path);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    // The running jobs still hold the evicted models, and they are
+    // released after the jobs finished
+    fn evict(&mut self) {
+        while self.entries.len() > 1 && self.memory_usage() > self.memory_budget {
+            let entry = self.entries.remove(0);
+            debug!("Evict Whisper model: {}", entry.key.model_path.display());
+        }
+    }
+}
+
+// Load the model or reuse the loaded one with the same context parameters.
+// The lock is held in loading, so the same model isn't loaded twice by the concurrent jobs.
+pub fn load_whisper_model(
+    model_path: impl AsRef<Path>,
+    params: WhisperContextParameters,
+) -> Result<Arc<WhisperContext>> {
+    let model_path = model_path.as_ref();
+    let key = ModelKey::new(model_path, &params);
+    let size = fs::metadata(model_path).map_or(0, |meta| meta.len());
+
+    WHISPER_MODELS.lock().unwrap().get_or_load(key, size, || {
+        debug!("Load Whisper model: {}", model_path.display());
+
+        WhisperContext::new_with_params(model_path.to_string_lossy().as_ref(), params)
+            .map_err(|e| anyhow!("Load Whisper model error: {e}"))
+    })
+}
+
+pub fn set_memory_budget(memory_budget: u64) {
+    WHISPER_MODELS
+        .lock()
+        .unwrap()
+        .set_memory_budget(memory_budget);
+}
+
+pub fn release_whisper_model(model_path: impl AsRef<Path>) {
+    WHISPER_MODELS.lock().unwrap().remove(model_path);
+}
+
+pub fn clear() {
+    WHISPER_MODELS.lock().unwrap().clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(name: &str) -> ModelKey {
+        ModelKey {
+            model_path: PathBuf::from(name),
+            modified: None,
+            use_gpu: false,
+            flash_attn: false,
+            gpu_device: 0,
+            dtw: String::default(),
+        }
+    }
+
+    #[test]
+    fn test_model_cache_reuse() -> Result<()> {
+        let mut cache = ModelCache::new(100);
+        let mut loaded = 0;
+
+        for _ in 0..3 {
+            let model = cache.get_or_load(key("base"), 10, || {
+                loaded += 1;
+                Ok("base".to_string())
+            })?;
+            assert_eq!(model.as_str(), "base");
+        }
+        assert_eq!(loaded, 1);
+
+        let gpu_key = ModelKey {
+            use_gpu: true,
+            ..key("base")
+        };
+        cache.get_or_load(gpu_key, 10, || Ok("base gpu".to_string()))?;
+        assert_eq!(cache.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_model_cache_lru_eviction() -> Result<()> {
+        let mut cache = ModelCache::new(25);
+
+        cache.get_or_load(key("tiny"), 10, || Ok(()))?;
+        cache.get_or_load(key("base"), 10, || Ok(()))?;
+        cache.get_or_load(key("tiny"), 10, || Ok(()))?;
+        cache.get_or_load(key("small"), 10, || Ok(()))?;
+
+        assert!(cache.contains(&key("tiny")));
+        assert!(!cache.contains(&key("base")));
+        assert!(cache.contains(&key("small")));
+        assert_eq!(cache.memory_usage(), 20);
+
+        cache.get_or_load(key("large"), 100, || Ok(()))?;
+        assert_eq!(cache.len(), 1);
+        assert!(cache.contains(&key("large")));
+
+        cache.remove("large");
+        assert!(cache.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_model_cache_load_error() {
+        let mut cache = ModelCache::<()>::new(100);
+
+        assert!(cache
+            .get_or_load(key("broken"), 10, || Err(anyhow!("broken model")))
+            .is_err());
+        assert!(cache.is_empty());
+    }
+}
@@ -1,27 +1,21 @@
-use super::whisper::{TranscriptionResult, TranscriptionSegment};
-use anyhow::{Context, Result};
+use super::whisper::{TranscriptionResult, TranscriptionSegment, TranscriptionWord};
+use anyhow::{bail, Context, Result};
 use chrono::{NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
 use std::{fs, path::Path};
 use unicode_segmentation::UnicodeSegmentation;
-use whisper_rs::SegmentCallbackData;
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Subtitle {
     pub index: i32,
     pub start_timestamp: u64,
     pub end_timestamp: u64,
     pub text: String,
-}
-
-impl From<SegmentCallbackData> for Subtitle {
-    fn from(segment: SegmentCallbackData) -> Self {
-        Subtitle {
-            index: segment.segment + 1,
-            start_timestamp: (segment.start_timestamp as u64) * 10,
-            end_timestamp: (segment.end_timestamp as u64) * 10,
-            text: segment.text,
-        }
-    }
+    pub translation: Option<String>,
+    pub words: Vec<TranscriptionWord>,
+    pub speaker: Option<String>,
+    pub confidence: Option<f32>, // (0.0-1.0)
 }
 
 impl From<&TranscriptionSegment> for Subtitle {
@@ -31,6 +25,75 @@ impl From<&TranscriptionSegment> for Subtitle {
             start_timestamp: segment.start_time,
             end_timestamp: segment.end_time,
             text: segment.text.clone(),
+            translation: None,
+            words: segment.words.clone(),
+            speaker: segment.speaker.clone(),
+            confidence: Some(segment.confidence),
+        }
+    }
+}
+
+// How the original text and the translation are exported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BilingualMode {
+    #[default]
+    OriginalOverTranslation,
+    TranslationOverOriginal,
+    OriginalOnly,
+    TranslationOnly,
+    Separate, // the original track and the translation track
+}
+
+impl BilingualMode {
+    // The subtitles without a translation keep the original text in the translation tracks,
+    // and `Separate` has only the original track if nothing is translated
+    pub fn tracks(&self, subtitles: &[Subtitle]) -> Vec<Vec<Subtitle>> {
+        let original_only = || {
+            subtitles
+                .iter()
+                .map(|item| Subtitle {
+                    translation: None,
+                    ..item.clone()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let translation_only = || {
+            subtitles
+                .iter()
+                .map(|item| Subtitle {
+                    text: item
+                        .translation
+                        .clone()
+                        .unwrap_or_else(|| item.text.clone()),
+                    translation: None,
+                    ..item.clone()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        match self {
+            BilingualMode::OriginalOverTranslation => vec![subtitles.to_vec()],
+            BilingualMode::TranslationOverOriginal => vec![subtitles
+                .iter()
+                .map(|item| match &item.translation {
+                    Some(translation) => Subtitle {
+                        text: translation.clone(),
+                        translation: Some(item.text.clone()),
+                        ..item.clone()
+                    },
+                    None => item.clone(),
+                })
+                .collect()],
+            BilingualMode::OriginalOnly => vec![original_only()],
+            BilingualMode::TranslationOnly => vec![translation_only()],
+            BilingualMode::Separate => {
+                if subtitles.iter().any(|item| item.translation.is_some()) {
+                    vec![original_only(), translation_only()]
+                } else {
+                    vec![original_only()]
+                }
+            }
         }
     }
 }
@@ -82,73 +145,1161 @@ pub fn valid_srt_timestamp(timestamp: &str) -> bool {
 }
 
 pub fn subtitle_to_srt(subtitle: &Subtitle) -> String {
+    srt_cue(subtitle, &text_with_translation(subtitle))
+}
+
+fn srt_cue(subtitle: &Subtitle, text: &str) -> String {
     format!(
-        "{}\n{} --> {}\n{}",
+        "{}\n{} --> {}\n{text}",
         subtitle.index,
         ms_to_srt_timestamp(subtitle.start_timestamp),
         ms_to_srt_timestamp(subtitle.end_timestamp),
-        subtitle.text
     )
 }
 
-pub fn subtitle_to_vtt(subtitle: &Subtitle) -> String {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VttAlign {
+    Start,
+    Center,
+    End,
+    Left,
+    Right,
+}
+
+impl VttAlign {
+    fn as_str(&self) -> &'static str {
+        match self {
+            VttAlign::Start => "start",
+            VttAlign::Center => "center",
+            VttAlign::End => "end",
+            VttAlign::Left => "left",
+            VttAlign::Right => "right",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VttLine {
+    Number(i32), // the negative number counts from the bottom of the video
+    Percent(u32),
+}
+
+// The font settings of the `::cue` pseudo-element in the STYLE block
+#[derive(Debug, Clone)]
+pub struct VttStyle {
+    pub font_name: String,
+    pub font_size: u32, // relative to the 480p video, the same as the video player
+    pub is_white_font_color: bool,
+    pub enable_background: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct VttConfig {
+    pub cue_identifier: bool,
+    pub position: Option<u32>, // percentage of the video width
+    pub line: Option<VttLine>,
+    pub align: Option<VttAlign>,
+    pub style: Option<VttStyle>,
+}
+
+impl Default for VttConfig {
+    fn default() -> Self {
+        Self {
+            cue_identifier: true,
+            position: None,
+            line: None,
+            align: None,
+            style: None,
+        }
+    }
+}
+
+impl VttConfig {
+    pub fn with_cue_identifier(mut self, cue_identifier: bool) -> Self {
+        self.cue_identifier = cue_identifier;
+        self
+    }
+
+    pub fn with_position(mut self, position: u32) -> Self {
+        self.position = Some(position.min(100));
+        self
+    }
+
+    pub fn with_line(mut self, line: VttLine) -> Self {
+        self.line = Some(match line {
+            VttLine::Percent(percent) => VttLine::Percent(percent.min(100)),
+            line => line,
+        });
+        self
+    }
+
+    pub fn with_align(mut self, align: VttAlign) -> Self {
+        self.align = Some(align);
+        self
+    }
+
+    pub fn with_style(mut self, style: VttStyle) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    // e.g. `position:10% line:-2 align:center`
+    fn cue_settings(&self) -> String {
+        let mut settings = vec![];
+
+        if let Some(position) = self.position {
+            settings.push(format!("position:{position}%"));
+        }
+
+        match self.line {
+            Some(VttLine::Number(number)) => settings.push(format!("line:{number}")),
+            Some(VttLine::Percent(percent)) => settings.push(format!("line:{percent}%")),
+            None => (),
+        }
+
+        if let Some(align) = self.align {
+            settings.push(format!("align:{}", align.as_str()));
+        }
+
+        settings.join(" ")
+    }
+
+    fn style_block(&self) -> Option<String> {
+        let style = self.style.as_ref()?;
+
+        // a block can't contain a blank line or `-->`
+        let font_name = style
+            .font_name
+            .chars()
+            .filter(|c| !matches!(c, '"' | '\\' | '\n' | '\r' | '<' | '>'))
+            .collect::<String>();
+
+        let (color, outline_color) = if style.is_white_font_color {
+            ("white", "black")
+        } else {
+            ("black", "white")
+        };
+
+        let background_color = if style.enable_background {
+            outline_color
+        } else {
+            "transparent"
+        };
+
+        let mut properties = vec![];
+        if !font_name.trim().is_empty() {
+            properties.push(format!("font-family: \"{}\";", font_name.trim()));
+        }
+        if style.font_size > 0 {
+            properties.push(format!(
+                "font-size: {:.2}vh;",
+                style.font_size as f32 * 100.0 / 480.0
+            ));
+        }
+        properties.push(format!("color: {color};"));
+        properties.push(format!("background-color: {background_color};"));
+        properties.push(format!("text-shadow: 0 0 2px {outline_color};"));
+
+        Some(format!(
+            "STYLE\n::cue {{\n{}\n}}",
+            properties
+                .iter()
+                .map(|item| format!("  {item}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ))
+    }
+}
+
+// The cue text can't contain a blank line, and `&`, `<` and `>` must be escaped
+fn escape_vtt_text(text: &str) -> String {
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+pub fn subtitle_to_vtt(subtitle: &Subtitle, config: &VttConfig) -> String {
+    // the voice span of WebVTT, e.g. `<v Speaker 1>hello`
+    let text = match &subtitle.speaker {
+        Some(speaker) => format!(
+            "<v {}>{}",
+            escape_vtt_text(&speaker.replace('\n', " ")),
+            escape_vtt_text(&text_with_translation(subtitle))
+        ),
+        None => escape_vtt_text(&text_with_translation(subtitle)),
+    };
+
+    let identifier = if config.cue_identifier {
+        format!("{}\n", subtitle.index)
+    } else {
+        String::default()
+    };
+
+    let settings = config.cue_settings();
+    let settings = if settings.is_empty() {
+        settings
+    } else {
+        format!(" {settings}")
+    };
+
     format!(
-        "{}\n{} --> {}\n{}",
-        subtitle.index,
+        "{identifier}{} --> {}{settings}\n{text}",
         ms_to_vtt_timestamp(subtitle.start_timestamp),
         ms_to_vtt_timestamp(subtitle.end_timestamp),
-        subtitle.text
     )
 }
 
 pub fn subtitle_to_plain(subtitle: &Subtitle) -> String {
-    format!("{}", subtitle.text)
+    text_with_translation(subtitle)
+}
+
+// The translation is below the original text
+fn text_with_translation(subtitle: &Subtitle) -> String {
+    match &subtitle.translation {
+        Some(translation) => format!("{}\n{translation}", subtitle.text),
+        None => subtitle.text.clone(),
+    }
+}
+
+// `Speaker 1: hello` for the formats without the speaker field
+fn text_with_speaker(subtitle: &Subtitle, speaker_prefix: bool) -> String {
+    match &subtitle.speaker {
+        Some(speaker) if speaker_prefix => {
+            format!("{speaker}: {}", text_with_translation(subtitle))
+        }
+        _ => text_with_translation(subtitle),
+    }
 }
 
 pub fn save_as_srt(subtitle: &[Subtitle], path: impl AsRef<Path>) -> Result<()> {
-    let contents = subtitle
-        .iter()
-        .map(|item| format!("{}\n\n", subtitle_to_srt(&item)))
-        .collect::<String>();
+    SrtExporter::default().save(subtitle, path.as_ref())
+}
+
+// The WebVTT file with the header, the optional STYLE block and the cues
+pub fn subtitles_to_vtt(subtitle: &[Subtitle], config: &VttConfig) -> String {
+    let mut contents = "WEBVTT\n\n".to_string();
+    if let Some(style) = config.style_block() {
+        contents.push_str(&format!("{style}\n\n"));
+    }
+
+    for item in subtitle.iter() {
+        contents.push_str(&format!("{}\n\n", subtitle_to_vtt(item, config)));
+    }
+
+    contents
+}
+
+pub fn save_as_vtt(
+    subtitle: &[Subtitle],
+    config: &VttConfig,
+    path: impl AsRef<Path>,
+) -> Result<()> {
+    config.save(subtitle, path.as_ref())
+}
+
+const ASS_ORIGINAL_STYLE: &str = "Original";
+const ASS_TRANSLATION_STYLE: &str = "Translation";
+
+// The same play resolution as the one of the ffmpeg `subtitles` filter,
+// so the font size looks the same as the burned subtitles
+const ASS_PLAY_RES_X: u32 = 384;
+const ASS_PLAY_RES_Y: u32 = 288;
+
+#[derive(Debug, Clone)]
+pub struct AssConfig {
+    pub font_name: String,
+    pub font_size: u32,
+    pub translation_font_size: Option<u32>, // the same as the font size if it's not set
+    pub is_white_font_color: bool,
+    pub enable_background: bool,
+    pub margin_v: u32,
+}
+
+impl Default for AssConfig {
+    fn default() -> Self {
+        Self {
+            font_name: "Source Han Sans SC Medium".to_string(),
+            font_size: 20,
+            translation_font_size: None,
+            is_white_font_color: true,
+            enable_background: false,
+            margin_v: 10,
+        }
+    }
+}
+
+impl AssConfig {
+    pub fn with_font_name(mut self, font_name: &str) -> Self {
+        self.font_name = font_name.to_string();
+        self
+    }
+
+    pub fn with_font_size(mut self, font_size: u32) -> Self {
+        self.font_size = font_size.max(1);
+        self
+    }
+
+    pub fn with_translation_font_size(mut self, font_size: u32) -> Self {
+        self.translation_font_size = Some(font_size.max(1));
+        self
+    }
+
+    pub fn with_is_white_font_color(mut self, is_white_font_color: bool) -> Self {
+        self.is_white_font_color = is_white_font_color;
+        self
+    }
+
+    pub fn with_enable_background(mut self, enable: bool) -> Self {
+        self.enable_background = enable;
+        self
+    }
+
+    pub fn with_margin_v(mut self, margin: u32) -> Self {
+        self.margin_v = margin;
+        self
+    }
+
+    // The opaque box of `BorderStyle=3` is drawn in the outline colour,
+    // and the colours are the same as the ones of `ffmpeg::add_subtitle`
+    fn style(&self, name: &str, font_size: u32) -> String {
+        let (primary_colour, outline_colour) = if self.is_white_font_color {
+            ("&H00FFFFFF", "&H00000000")
+        } else {
+            ("&H00000000", "&H00FFFFFF")
+        };
 
-    fs::write(path.as_ref(), contents)
-        .with_context(|| format!("Save {} failed", path.as_ref().display()))?;
+        let (back_colour, border_style) = if self.enable_background {
+            (outline_colour, 3)
+        } else {
+            ("&H80000000", 1)
+        };
 
-    Ok(())
+        let font_name = self.font_name.replace(',', " ");
+        format!(
+            "Style: {name},{font_name},{font_size},{primary_colour},&H000000FF,{outline_colour},{back_colour},0,0,0,0,100,100,0,0,{border_style},1,0,2,10,10,{},1",
+            self.margin_v
+        )
+    }
 }
 
-pub fn save_as_vtt(subtitle: &[Subtitle], path: impl AsRef<Path>) -> Result<()> {
-    let contents = subtitle
-        .iter()
-        .map(|item| format!("{}\n\n", subtitle_to_vtt(&item)))
-        .collect::<String>();
+pub fn ms_to_ass_timestamp(milliseconds: u64) -> String {
+    let total_seconds = milliseconds / 1000;
+
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        total_seconds / 3600,
+        (total_seconds % 3600) / 60,
+        total_seconds % 60,
+        (milliseconds % 1000) / 10
+    )
+}
 
-    fs::write(path.as_ref(), contents)
-        .with_context(|| format!("Save {} failed", path.as_ref().display()))?;
+// The line breaks are `\N`, and the braces are escaped, otherwise they are override blocks
+fn escape_ass_text(text: &str) -> String {
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\\N")
+        .replace('{', "\\{")
+        .replace('}', "\\}")
+}
+
+// The translation is in the same event with the original text,
+// and it's switched to the translation style by `{\rTranslation}`
+pub fn subtitle_to_ass(subtitle: &Subtitle) -> String {
+    let mut text = escape_ass_text(&subtitle.text);
+    if let Some(translation) = &subtitle.translation {
+        text.push_str(&format!(
+            "\\N{{\\r{ASS_TRANSLATION_STYLE}}}{}",
+            escape_ass_text(translation)
+        ));
+    }
+
+    format!(
+        "Dialogue: 0,{},{},{ASS_ORIGINAL_STYLE},{},0,0,0,,{text}",
+        ms_to_ass_timestamp(subtitle.start_timestamp),
+        ms_to_ass_timestamp(subtitle.end_timestamp),
+        subtitle
+            .speaker
+            .as_deref()
+            .unwrap_or_default()
+            .replace([',', '\n'], " "),
+    )
+}
+
+pub fn subtitles_to_ass(subtitle: &[Subtitle], config: &AssConfig) -> String {
+    let mut contents = format!(
+        "[Script Info]\nScriptType: v4.00+\nPlayResX: {ASS_PLAY_RES_X}\nPlayResY: {ASS_PLAY_RES_Y}\nWrapStyle: 0\nScaledBorderAndShadow: yes\n\n"
+    );
+
+    contents.push_str("[V4+ Styles]\nFormat: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n");
+    contents.push_str(&format!(
+        "{}\n{}\n\n",
+        config.style(ASS_ORIGINAL_STYLE, config.font_size),
+        config.style(
+            ASS_TRANSLATION_STYLE,
+            config.translation_font_size.unwrap_or(config.font_size)
+        )
+    ));
+
+    contents.push_str("[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n");
+    for item in subtitle.iter() {
+        contents.push_str(&format!("{}\n", subtitle_to_ass(item)));
+    }
+
+    contents
+}
 
-    Ok(())
+pub fn save_as_ass(
+    subtitle: &[Subtitle],
+    config: &AssConfig,
+    path: impl AsRef<Path>,
+) -> Result<()> {
+    config.save(subtitle, path.as_ref())
 }
 
 pub fn save_as_txt(subtitle: &[Subtitle], path: impl AsRef<Path>) -> Result<()> {
-    let contents = subtitle
-        .iter()
-        .map(|item| format!("{}\n\n", subtitle_to_plain(&item)))
-        .collect::<String>();
+    TxtExporter::default().save(subtitle, path.as_ref())
+}
+
+// A subtitle file format. The exporters of the formats are got by `exporter`,
+// and the ones with the settings, e.g. `VttConfig`, are created by the callers.
+pub trait Exporter: Send + Sync {
+    fn extension(&self) -> &'static str;
+
+    fn export(&self, subtitles: &[Subtitle]) -> Result<String>;
+
+    fn save(&self, subtitles: &[Subtitle], path: &Path) -> Result<()> {
+        fs::write(path, self.export(subtitles)?)
+            .with_context(|| format!("Save {} failed", path.display()))?;
+
+        Ok(())
+    }
+}
+
+pub const EXPORTER_EXTENSIONS: [&str; 9] = [
+    "srt", "vtt", "ass", "txt", "json", "csv", "ttml", "sbv", "lrc",
+];
+
+pub fn exporter(extension: &str) -> Option<Box<dyn Exporter>> {
+    exporter_with_speaker_prefix(extension, false)
+}
+
+// The speakers are only written into the text of srt, txt and sbv if they are prefixed,
+// since the prefixes can't be told apart from the text when the files are imported
+pub fn exporter_with_speaker_prefix(
+    extension: &str,
+    speaker_prefix: bool,
+) -> Option<Box<dyn Exporter>> {
+    let exporter: Box<dyn Exporter> = match extension.to_lowercase().as_str() {
+        "srt" => Box::new(SrtExporter { speaker_prefix }),
+        "vtt" => Box::new(VttConfig::default()),
+        "ass" => Box::new(AssConfig::default()),
+        "txt" => Box::new(TxtExporter { speaker_prefix }),
+        "json" => Box::new(JsonExporter),
+        "csv" => Box::new(CsvExporter),
+        "ttml" | "dfxp" => Box::new(TtmlExporter::default()),
+        "sbv" => Box::new(SbvExporter { speaker_prefix }),
+        "lrc" => Box::new(LrcExporter),
+        _ => return None,
+    };
+
+    Some(exporter)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SrtExporter {
+    pub speaker_prefix: bool,
+}
+
+impl Exporter for SrtExporter {
+    fn extension(&self) -> &'static str {
+        "srt"
+    }
+
+    fn export(&self, subtitles: &[Subtitle]) -> Result<String> {
+        Ok(subtitles
+            .iter()
+            .map(|item| {
+                let text = text_with_speaker(item, self.speaker_prefix);
+                format!("{}\n\n", srt_cue(item, &text))
+            })
+            .collect())
+    }
+}
+
+impl Exporter for VttConfig {
+    fn extension(&self) -> &'static str {
+        "vtt"
+    }
+
+    fn export(&self, subtitles: &[Subtitle]) -> Result<String> {
+        Ok(subtitles_to_vtt(subtitles, self))
+    }
+}
 
-    fs::write(path.as_ref(), contents)
-        .with_context(|| format!("Save {} failed", path.as_ref().display()))?;
+impl Exporter for AssConfig {
+    fn extension(&self) -> &'static str {
+        "ass"
+    }
+
+    fn export(&self, subtitles: &[Subtitle]) -> Result<String> {
+        Ok(subtitles_to_ass(subtitles, self))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TxtExporter {
+    pub speaker_prefix: bool,
+}
+
+impl Exporter for TxtExporter {
+    fn extension(&self) -> &'static str {
+        "txt"
+    }
+
+    fn export(&self, subtitles: &[Subtitle]) -> Result<String> {
+        Ok(subtitles
+            .iter()
+            .map(|item| format!("{}\n\n", text_with_speaker(item, self.speaker_prefix)))
+            .collect())
+    }
+}
+
+// All the fields of the subtitles are kept, and the file could be imported again
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonExporter;
+
+impl Exporter for JsonExporter {
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn export(&self, subtitles: &[Subtitle]) -> Result<String> {
+        serde_json::to_string_pretty(subtitles).context("Serialize subtitles to json failed")
+    }
+}
+
+// The UTF-8 BOM makes Excel read the non-ASCII text correctly
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CsvExporter;
+
+impl Exporter for CsvExporter {
+    fn extension(&self) -> &'static str {
+        "csv"
+    }
+
+    fn export(&self, subtitles: &[Subtitle]) -> Result<String> {
+        let escape = |field: &str| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        };
+
+        let mut contents =
+            "\u{feff}index,start,end,speaker,text,translation,confidence\r\n".to_string();
+        for item in subtitles.iter() {
+            let fields = [
+                item.index.to_string(),
+                ms_to_vtt_timestamp(item.start_timestamp),
+                ms_to_vtt_timestamp(item.end_timestamp),
+                item.speaker.clone().unwrap_or_default(),
+                item.text.clone(),
+                item.translation.clone().unwrap_or_default(),
+                item.confidence
+                    .map(|confidence| format!("{confidence:.2}"))
+                    .unwrap_or_default(),
+            ];
+
+            contents.push_str(&format!(
+                "{}\r\n",
+                fields
+                    .iter()
+                    .map(|field| escape(field))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ));
+        }
+
+        Ok(contents)
+    }
+}
+
+// The speakers are the `ttm:agent` elements, and the paragraphs refer to them
+#[derive(Debug, Clone, Default)]
+pub struct TtmlExporter {
+    pub lang: String, // e.g. `en`, it's empty if the language is unknown
+}
+
+impl TtmlExporter {
+    pub fn with_lang(mut self, lang: &str) -> Self {
+        self.lang = lang.to_string();
+        self
+    }
+}
+
+impl Exporter for TtmlExporter {
+    fn extension(&self) -> &'static str {
+        "ttml"
+    }
+
+    fn export(&self, subtitles: &[Subtitle]) -> Result<String> {
+        let mut speakers: Vec<&str> = vec![];
+        for speaker in subtitles.iter().filter_map(|item| item.speaker.as_deref()) {
+            if !speakers.contains(&speaker) {
+                speakers.push(speaker);
+            }
+        }
+
+        let mut contents = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<tt xmlns=\"http://www.w3.org/ns/ttml\" xmlns:ttm=\"http://www.w3.org/ns/ttml#metadata\" xml:lang=\"{}\">\n",
+            escape_xml(&self.lang)
+        );
+
+        if !speakers.is_empty() {
+            contents.push_str("  <head>\n    <metadata>\n");
+            for (index, speaker) in speakers.iter().enumerate() {
+                contents.push_str(&format!(
+                    "      <ttm:agent xml:id=\"speaker_{}\" type=\"person\">\n        <ttm:name type=\"full\">{}</ttm:name>\n      </ttm:agent>\n",
+                    index + 1,
+                    escape_xml(speaker)
+                ));
+            }
+            contents.push_str("    </metadata>\n  </head>\n");
+        }
+
+        contents.push_str("  <body>\n    <div>\n");
+        for item in subtitles.iter() {
+            let agent = item
+                .speaker
+                .as_deref()
+                .and_then(|speaker| speakers.iter().position(|item| *item == speaker))
+                .map(|index| format!(" ttm:agent=\"speaker_{}\"", index + 1))
+                .unwrap_or_default();
+
+            let text = text_with_translation(item)
+                .lines()
+                .map(escape_xml)
+                .collect::<Vec<_>>()
+                .join("<br/>");
+
+            contents.push_str(&format!(
+                "      <p begin=\"{}\" end=\"{}\"{agent}>{text}</p>\n",
+                ms_to_vtt_timestamp(item.start_timestamp),
+                ms_to_vtt_timestamp(item.end_timestamp),
+            ));
+        }
+        contents.push_str("    </div>\n  </body>\n</tt>\n");
+
+        Ok(contents)
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
 
-    Ok(())
+// The YouTube subtitle format, e.g. `0:00:01.000,0:00:02.500`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SbvExporter {
+    pub speaker_prefix: bool,
+}
+
+impl Exporter for SbvExporter {
+    fn extension(&self) -> &'static str {
+        "sbv"
+    }
+
+    fn export(&self, subtitles: &[Subtitle]) -> Result<String> {
+        let timestamp = |milliseconds: u64| {
+            let total_seconds = milliseconds / 1000;
+            format!(
+                "{}:{:02}:{:02}.{:03}",
+                total_seconds / 3600,
+                (total_seconds % 3600) / 60,
+                total_seconds % 60,
+                milliseconds % 1000
+            )
+        };
+
+        Ok(subtitles
+            .iter()
+            .map(|item| {
+                // a blank line ends the caption
+                let text = text_with_speaker(item, self.speaker_prefix)
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                format!(
+                    "{},{}\n{text}\n\n",
+                    timestamp(item.start_timestamp),
+                    timestamp(item.end_timestamp)
+                )
+            })
+            .collect())
+    }
+}
+
+// The lyrics only have the start time, so an empty line is added
+// at the end time if there is a gap before the next line
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LrcExporter;
+
+impl Exporter for LrcExporter {
+    fn extension(&self) -> &'static str {
+        "lrc"
+    }
+
+    fn export(&self, subtitles: &[Subtitle]) -> Result<String> {
+        let timestamp = |milliseconds: u64| {
+            format!(
+                "[{:02}:{:02}.{:02}]",
+                milliseconds / 60_000,
+                (milliseconds % 60_000) / 1000,
+                (milliseconds % 1000) / 10
+            )
+        };
+
+        let mut contents = String::new();
+        for (index, item) in subtitles.iter().enumerate() {
+            let text = text_with_translation(item)
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join(" / ");
+
+            contents.push_str(&format!("{}{text}\n", timestamp(item.start_timestamp)));
+
+            let has_gap = subtitles
+                .get(index + 1)
+                .is_none_or(|next| next.start_timestamp > item.end_timestamp);
+            if has_gap {
+                contents.push_str(&format!("{}\n", timestamp(item.end_timestamp)));
+            }
+        }
+
+        Ok(contents)
+    }
+}
+
+// Read a subtitle file, the format is decided by the extension of the file
+pub fn load_subtitle_file(path: impl AsRef<Path>) -> Result<Vec<Subtitle>> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).with_context(|| format!("Read {} failed", path.display()))?;
+    let contents = decode_subtitle_bytes(&bytes);
+
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "srt" => parse_srt(&contents),
+        "vtt" => parse_vtt(&contents),
+        "ass" | "ssa" => parse_ass(&contents),
+        "json" => parse_json(&contents),
+        _ => bail!("Unsupported subtitle format: {}", path.display()),
+    }
+}
+
+// The subtitle files written by the Windows tools are usually with a BOM,
+// and some of them are in UTF-16
+fn decode_subtitle_bytes(bytes: &[u8]) -> String {
+    let decode_utf16 = |bytes: &[u8], to_u16: fn([u8; 2]) -> u16| {
+        let units = bytes
+            .chunks_exact(2)
+            .map(|pair| to_u16([pair[0], pair[1]]))
+            .collect::<Vec<_>>();
+        String::from_utf16_lossy(&units)
+    };
+
+    match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8_lossy(rest).to_string(),
+        [0xFF, 0xFE, rest @ ..] => decode_utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => decode_utf16(rest, u16::from_be_bytes),
+        _ => String::from_utf8_lossy(bytes).to_string(),
+    }
+}
+
+fn normalize_subtitle_contents(contents: &str) -> String {
+    contents
+        .trim_start_matches('\u{feff}')
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+}
+
+// Accept `hh:mm:ss,mmm`, `hh:mm:ss.mmm`, `mm:ss.mmm` and the centiseconds of ASS `h:mm:ss.cc`
+fn parse_timestamp(timestamp: &str) -> Option<u64> {
+    let timestamp = timestamp.trim();
+    let (clock, fraction) = match timestamp.rfind([',', '.']) {
+        Some(index) => (&timestamp[..index], &timestamp[index + 1..]),
+        None => (timestamp, ""),
+    };
+
+    let parts = clock
+        .split(':')
+        .map(|part| part.trim().parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    let (hours, minutes, seconds) = match parts[..] {
+        [hours, minutes, seconds] => (hours, minutes, seconds),
+        [minutes, seconds] => (0, minutes, seconds),
+        _ => return None,
+    };
+
+    if minutes >= 60 || seconds >= 60 {
+        return None;
+    }
+
+    let millis = if fraction.is_empty() {
+        0
+    } else if fraction.len() <= 3 && fraction.chars().all(|c| c.is_ascii_digit()) {
+        // `.5` is 500ms and `.05` is 50ms
+        fraction.parse::<u64>().ok()? * 10_u64.pow(3 - fraction.len() as u32)
+    } else {
+        return None;
+    };
+
+    Some(hours * 3_600_000 + minutes * 60_000 + seconds * 1000 + millis)
+}
+
+// `00:00:01,000 --> 00:00:02,000 X1:100 ...`, the text after the end timestamp is ignored
+fn parse_timing_line(line: &str) -> Option<(u64, u64)> {
+    let (start, rest) = line.split_once("-->")?;
+    let end = rest.split_whitespace().next()?;
+
+    Some((parse_timestamp(start)?, parse_timestamp(end)?))
+}
+
+// The cues are sorted by the start timestamp and reindexed from 1
+fn finish_parsing(mut subtitles: Vec<Subtitle>, format: &str) -> Result<Vec<Subtitle>> {
+    if subtitles.is_empty() {
+        bail!("No subtitle is found in the {format} file");
+    }
+
+    subtitles.sort_by_key(|item| (item.start_timestamp, item.end_timestamp));
+    for (index, item) in subtitles.iter_mut().enumerate() {
+        item.index = index as i32 + 1;
+    }
+
+    Ok(subtitles)
+}
+
+// The subtitles written by `JsonExporter`
+pub fn parse_json(contents: &str) -> Result<Vec<Subtitle>> {
+    let subtitles = serde_json::from_str::<Vec<Subtitle>>(contents.trim_start_matches('\u{feff}'))
+        .context("Parse json subtitles failed")?;
+
+    finish_parsing(subtitles, "json")
+}
+
+// The index lines are not trusted, because they are missing,
+// duplicated or not numbers in the files written by hand
+pub fn parse_srt(contents: &str) -> Result<Vec<Subtitle>> {
+    let contents = normalize_subtitle_contents(contents);
+    let lines = contents.lines().collect::<Vec<_>>();
+    let mut subtitles = vec![];
+    let mut index = 0;
+
+    while index < lines.len() {
+        let Some((start_timestamp, end_timestamp)) = parse_timing_line(lines[index]) else {
+            index += 1;
+            continue;
+        };
+
+        index += 1;
+        let mut text_lines = vec![];
+        while index < lines.len() && !lines[index].trim().is_empty() {
+            // a cue without the blank line before the next one
+            if parse_timing_line(lines[index]).is_some() {
+                break;
+            }
+            text_lines.push(lines[index].trim());
+            index += 1;
+        }
+
+        // the index line of the next cue is not the text of this cue
+        if index < lines.len()
+            && parse_timing_line(lines[index]).is_some()
+            && text_lines
+                .last()
+                .is_some_and(|line| line.parse::<i64>().is_ok())
+        {
+            text_lines.pop();
+        }
+
+        let (text, speaker) = split_speaker(&text_lines.join("\n"));
+        subtitles.push(Subtitle {
+            start_timestamp,
+            end_timestamp,
+            text,
+            speaker,
+            ..Default::default()
+        });
+    }
+
+    finish_parsing(subtitles, "srt")
+}
+
+// The blocks are separated by blank lines. The NOTE, STYLE and REGION blocks are skipped,
+// and the cue identifier is the optional line before the timing line.
+pub fn parse_vtt(contents: &str) -> Result<Vec<Subtitle>> {
+    let contents = normalize_subtitle_contents(contents);
+    let mut subtitles = vec![];
+
+    for block in contents.split("\n\n") {
+        let lines = block
+            .lines()
+            .skip_while(|line| line.trim().is_empty())
+            .collect::<Vec<_>>();
+
+        let Some(first_line) = lines.first() else {
+            continue;
+        };
+
+        if ["NOTE", "STYLE", "REGION"].iter().any(|keyword| {
+            first_line
+                .strip_prefix(keyword)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+        }) {
+            continue;
+        }
+
+        // the header block has no timing line, and the cue identifier is before the timing line
+        let Some(timing_index) = lines.iter().take(2).position(|line| line.contains("-->")) else {
+            continue;
+        };
+
+        let Some((start_timestamp, end_timestamp)) = parse_timing_line(lines[timing_index]) else {
+            continue;
+        };
+
+        let text = lines[timing_index + 1..]
+            .iter()
+            .map(|line| line.trim())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let (text, speaker) = parse_vtt_cue_text(&text);
+        subtitles.push(Subtitle {
+            start_timestamp,
+            end_timestamp,
+            text,
+            speaker,
+            ..Default::default()
+        });
+    }
+
+    finish_parsing(subtitles, "vtt")
+}
+
+// `<v Speaker 1>hello` and `<v.loud Speaker 1>hello</v>` are the voice spans
+fn parse_vtt_cue_text(text: &str) -> (String, Option<String>) {
+    let mut speaker = None;
+    let mut output = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find('<') {
+        output.push_str(&rest[..start]);
+
+        let Some(end) = rest[start..].find('>') else {
+            output.push_str(&rest[start..]);
+            rest = "";
+            break;
+        };
+
+        let tag = &rest[start + 1..start + end];
+        if speaker.is_none() && (tag.starts_with("v ") || tag.starts_with("v.")) {
+            speaker = tag
+                .split_once(' ')
+                .map(|(_, name)| name.trim().to_string())
+                .filter(|name| !name.is_empty());
+        }
+
+        rest = &rest[start + end + 1..];
+    }
+    output.push_str(rest);
+
+    (
+        decode_vtt_entities(&output).trim().to_string(),
+        speaker.map(|speaker| decode_vtt_entities(&speaker)),
+    )
+}
+
+fn decode_vtt_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&lrm;", "\u{200e}")
+        .replace("&rlm;", "\u{200f}")
+        .replace("&amp;", "&")
+}
+
+// Only the Dialogue lines of the [Events] section are read, and the
+// fields are located by the Format line of the section
+pub fn parse_ass(contents: &str) -> Result<Vec<Subtitle>> {
+    let contents = normalize_subtitle_contents(contents);
+    let mut subtitles = vec![];
+    let mut in_events = false;
+    let mut fields = [
+        "Layer", "Start", "End", "Style", "Name", "MarginL", "MarginR", "MarginV", "Effect", "Text",
+    ]
+    .iter()
+    .map(|field| field.to_lowercase())
+    .collect::<Vec<_>>();
+
+    for line in contents.lines() {
+        let line = line.trim();
+
+        if line.starts_with('[') {
+            in_events = line.eq_ignore_ascii_case("[events]");
+            continue;
+        }
+
+        if !in_events {
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+
+        match key.trim() {
+            "Format" => {
+                fields = value
+                    .split(',')
+                    .map(|field| field.trim().to_lowercase())
+                    .collect();
+            }
+            "Dialogue" => {
+                // the text is the last field, and it may contain commas
+                let values = value
+                    .trim_start()
+                    .splitn(fields.len(), ',')
+                    .collect::<Vec<_>>();
+                let field = |name: &str| {
+                    fields
+                        .iter()
+                        .position(|field| field == name)
+                        .and_then(|index| values.get(index))
+                        .map(|value| value.trim())
+                };
+
+                let (Some(start), Some(end), Some(text)) =
+                    (field("start"), field("end"), field("text"))
+                else {
+                    continue;
+                };
+
+                let (Some(start_timestamp), Some(end_timestamp)) =
+                    (parse_timestamp(start), parse_timestamp(end))
+                else {
+                    continue;
+                };
+
+                // the bilingual subtitles written by `subtitle_to_ass`
+                let (text, translation) =
+                    match text.split_once(&format!("\\N{{\\r{ASS_TRANSLATION_STYLE}}}")) {
+                        Some((text, translation)) => (
+                            strip_ass_text(text),
+                            Some(strip_ass_text(translation)).filter(|item| !item.is_empty()),
+                        ),
+                        None => (strip_ass_text(text), None),
+                    };
+
+                if text.is_empty() {
+                    continue;
+                }
+
+                subtitles.push(Subtitle {
+                    start_timestamp,
+                    end_timestamp,
+                    text,
+                    translation,
+                    speaker: field("name")
+                        .filter(|name| !name.is_empty())
+                        .map(|name| name.to_string()),
+                    ..Default::default()
+                });
+            }
+            _ => (),
+        }
+    }
+
+    finish_parsing(subtitles, "ass")
+}
+
+// Remove the override blocks, e.g. `{\an8}`, and convert the escaped line breaks and spaces
+fn strip_ass_text(text: &str) -> String {
+    let mut output = String::new();
+    let mut in_block = false;
+
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if !in_block => match chars.next() {
+                Some(next @ ('{' | '}')) => output.push(next),
+                Some(next) => {
+                    output.push(c);
+                    output.push(next);
+                }
+                None => output.push(c),
+            },
+            '{' => in_block = true,
+            '}' if in_block => in_block = false,
+            _ if !in_block => output.push(c),
+            _ => (),
+        }
+    }
+
+    output
+        .replace("\\N", "\n")
+        .replace("\\n", "\n")
+        .replace("\\h", " ")
+        .trim()
+        .to_string()
+}
+
+// `Speaker 1: hello` is written by `SrtExporter` with the speaker prefix
+fn split_speaker(text: &str) -> (String, Option<String>) {
+    match text.split_once(": ") {
+        Some((speaker, content))
+            if speaker
+                .strip_prefix("Speaker ")
+                .is_some_and(|number| number.parse::<u32>().is_ok()) =>
+        {
+            (content.to_string(), Some(speaker.to_string()))
+        }
+        _ => (text.to_string(), None),
+    }
 }
 
 pub fn convert_traditional_to_simplified_chinese(text: &str) -> String {
     fast2s::convert(text)
 }
 
+// The cut is put at the word boundary if the word timings match the content,
+// otherwise the split time is estimated from the character proportion
 pub fn split_subtitle_into_two(
     start_timestamp: u64,
     end_timestamp: u64,
     content: &str,
+    words: &[TranscriptionWord],
 ) -> Option<((u64, u64, String), (u64, u64, String))> {
     if content.is_empty() || content.trim().len() <= 1 {
         return None;
@@ -186,26 +1337,383 @@ pub fn split_subtitle_into_two(
         (first_part, second_part)
     };
 
-    let total_chars = content.chars().count();
-    let first_part_chars = first_part.chars().count();
+    let (first_end_timestamp, second_start_timestamp) =
+        match split_timestamp_by_words(words, content, &first_part) {
+            Some((first_end, second_start)) => (
+                first_end.clamp(start_timestamp, end_timestamp),
+                second_start.clamp(start_timestamp, end_timestamp),
+            ),
+            None => {
+                let total_chars = content.chars().count();
+                let first_part_chars = first_part.chars().count();
 
-    let duration = end_timestamp - start_timestamp;
-    let split_time = start_timestamp + (duration * first_part_chars as u64) / total_chars as u64;
+                let duration = end_timestamp - start_timestamp;
+                let split_time =
+                    start_timestamp + (duration * first_part_chars as u64) / total_chars as u64;
+                (split_time, split_time)
+            }
+        };
 
     Some((
-        (start_timestamp, split_time, first_part),
-        (split_time, end_timestamp, second_part),
+        (start_timestamp, first_end_timestamp, first_part),
+        (second_start_timestamp, end_timestamp, second_part),
     ))
 }
 
+fn split_timestamp_by_words(
+    words: &[TranscriptionWord],
+    content: &str,
+    first_part: &str,
+) -> Option<(u64, u64)> {
+    let strip = |text: &str| {
+        text.chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+    };
+
+    // the words are outdated after editing the content
+    let words_text = words.iter().map(|w| w.text.as_str()).collect::<String>();
+    if words.is_empty() || strip(&words_text) != strip(content) {
+        return None;
+    }
+
+    let target_chars = strip(first_part).chars().count();
+    let mut chars = 0;
+
+    for (index, word) in words.iter().enumerate() {
+        chars += strip(&word.text).chars().count();
+
+        if chars == target_chars {
+            let next_word = words.get(index + 1)?;
+            return Some((word.end_time, next_word.start_time.max(word.end_time)));
+        } else if chars > target_chars {
+            // the cut is inside a word
+            return None;
+        }
+    }
+
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_subtitle_with_speaker() {
+        let mut subtitle = Subtitle {
+            index: 1,
+            start_timestamp: 1000,
+            end_timestamp: 2500,
+            text: "Hello".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            subtitle_to_vtt(&subtitle, &VttConfig::default()),
+            "1\n00:00:01.000 --> 00:00:02.500\nHello"
+        );
+
+        subtitle.speaker = Some("Speaker 1".to_string());
+        assert_eq!(
+            subtitle_to_vtt(&subtitle, &VttConfig::default()),
+            "1\n00:00:01.000 --> 00:00:02.500\n<v Speaker 1>Hello"
+        );
+        assert_eq!(
+            subtitle_to_srt(&subtitle),
+            "1\n00:00:01,000 --> 00:00:02,500\nHello"
+        );
+
+        // the speaker is only in the text if it's prefixed
+        let subtitles = [subtitle];
+        let export = |speaker_prefix: bool| {
+            exporter_with_speaker_prefix("srt", speaker_prefix)
+                .unwrap()
+                .export(&subtitles)
+                .unwrap()
+        };
+
+        let contents = export(true);
+        assert_eq!(
+            contents,
+            "1\n00:00:01,000 --> 00:00:02,500\nSpeaker 1: Hello\n\n"
+        );
+        assert_eq!(
+            parse_srt(&contents).unwrap()[0].speaker.as_deref(),
+            Some("Speaker 1")
+        );
+        assert_eq!(export(false), "1\n00:00:01,000 --> 00:00:02,500\nHello\n\n");
+        assert_eq!(
+            exporter_with_speaker_prefix("txt", true)
+                .unwrap()
+                .export(&subtitles)
+                .unwrap(),
+            "Speaker 1: Hello\n\n"
+        );
+    }
+
+    #[test]
+    fn test_bilingual_mode_tracks() {
+        let subtitles = vec![
+            Subtitle {
+                index: 1,
+                text: "Hello".to_string(),
+                translation: Some("你好".to_string()),
+                ..Default::default()
+            },
+            Subtitle {
+                index: 2,
+                text: "Bye".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let texts = |mode: BilingualMode| {
+            mode.tracks(&subtitles)
+                .iter()
+                .map(|track| track.iter().map(subtitle_to_plain).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            texts(BilingualMode::OriginalOverTranslation),
+            vec![vec!["Hello\n你好", "Bye"]]
+        );
+        assert_eq!(
+            texts(BilingualMode::TranslationOverOriginal),
+            vec![vec!["你好\nHello", "Bye"]]
+        );
+        assert_eq!(
+            texts(BilingualMode::OriginalOnly),
+            vec![vec!["Hello", "Bye"]]
+        );
+        assert_eq!(
+            texts(BilingualMode::TranslationOnly),
+            vec![vec!["你好", "Bye"]]
+        );
+        assert_eq!(
+            texts(BilingualMode::Separate),
+            vec![vec!["Hello", "Bye"], vec!["你好", "Bye"]]
+        );
+        assert_eq!(BilingualMode::Separate.tracks(&subtitles[1..]).len(), 1);
+    }
+
+    #[test]
+    fn test_parse_srt() {
+        let contents = "\u{feff}1\r\n00:00:01,000 --> 00:00:02,500\r\nHello\r\nworld\r\n\r\nx\r\n00:00:03,000 --> 00:00:04,000\r\nSpeaker 2: Hi\r\n7\r\n00:00:00,500 --> 00:00:00,900 X1:100\r\nFirst\r\n";
+        let subtitles = parse_srt(contents).unwrap();
+
+        assert_eq!(subtitles.len(), 3);
+        assert_eq!(subtitles[0].index, 1);
+        assert_eq!(subtitles[0].start_timestamp, 500);
+        assert_eq!(subtitles[0].text, "First");
+        assert_eq!(subtitles[1].text, "Hello\nworld");
+        assert_eq!(subtitles[1].end_timestamp, 2500);
+        assert_eq!(subtitles[2].text, "Hi");
+        assert_eq!(subtitles[2].speaker.as_deref(), Some("Speaker 2"));
+
+        assert!(parse_srt("no subtitle").is_err());
+    }
+
+    #[test]
+    fn test_parse_vtt() {
+        let contents = "WEBVTT - title\nKind: captions\n\nSTYLE\n::cue { color: yellow; }\n\nNOTE a comment\n00:00:09.000 --> 00:00:10.000\n\nintro\n00:01.000 --> 00:02.500 position:10% align:start\n<v.loud Speaker 1>Hello <b>world</b> &amp; you</v>\n\n00:00:03.000 --> 00:00:04.000 line:0\nBye\n";
+        let subtitles = parse_vtt(contents).unwrap();
+
+        assert_eq!(subtitles.len(), 2);
+        assert_eq!(subtitles[0].start_timestamp, 1000);
+        assert_eq!(subtitles[0].end_timestamp, 2500);
+        assert_eq!(subtitles[0].text, "Hello world & you");
+        assert_eq!(subtitles[0].speaker.as_deref(), Some("Speaker 1"));
+        assert_eq!(subtitles[1].index, 2);
+        assert_eq!(subtitles[1].text, "Bye");
+        assert_eq!(subtitles[1].speaker, None);
+    }
+
+    #[test]
+    fn test_vtt_round_trip() {
+        let subtitles = vec![
+            Subtitle {
+                index: 1,
+                start_timestamp: 1000,
+                end_timestamp: 2500,
+                text: "Tom & Jerry <3".to_string(),
+                speaker: Some("Speaker 1".to_string()),
+                ..Default::default()
+            },
+            Subtitle {
+                index: 2,
+                start_timestamp: 3_723_004,
+                end_timestamp: 3_724_000,
+                text: "a --> b\n\nsecond line".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let config = VttConfig::default()
+            .with_position(150)
+            .with_line(VttLine::Number(-2))
+            .with_align(VttAlign::Center)
+            .with_style(VttStyle {
+                font_name: "Source \"Han\" Sans".to_string(),
+                font_size: 24,
+                is_white_font_color: true,
+                enable_background: true,
+            });
+
+        let contents = subtitles_to_vtt(&subtitles, &config);
+        assert!(contents.starts_with("WEBVTT\n\nSTYLE\n::cue {\n  font-family: \"Source Han Sans\";\n  font-size: 5.00vh;\n  color: white;\n  background-color: black;"));
+        assert!(contents.contains("\n1\n00:00:01.000 --> 00:00:02.500 position:100% line:-2 align:center\n<v Speaker 1>Tom &amp; Jerry &lt;3\n\n"));
+        assert!(contents.contains("01:02:03.004 --> 01:02:04.000 position:100% line:-2 align:center\na --&gt; b\nsecond line\n\n"));
+
+        let parsed = parse_vtt(&contents).unwrap();
+        assert_eq!(parsed.len(), 2);
+        for (item, subtitle) in parsed.iter().zip(subtitles.iter()) {
+            assert_eq!(item.index, subtitle.index);
+            assert_eq!(item.start_timestamp, subtitle.start_timestamp);
+            assert_eq!(item.end_timestamp, subtitle.end_timestamp);
+            assert_eq!(item.speaker, subtitle.speaker);
+        }
+        assert_eq!(parsed[0].text, "Tom & Jerry <3");
+        assert_eq!(parsed[1].text, "a --> b\nsecond line");
+
+        let config = VttConfig::default().with_cue_identifier(false);
+        let contents = subtitles_to_vtt(&subtitles[..1], &config);
+        assert_eq!(
+            contents,
+            "WEBVTT\n\n00:00:01.000 --> 00:00:02.500\n<v Speaker 1>Tom &amp; Jerry &lt;3\n\n"
+        );
+        assert_eq!(parse_vtt(&contents).unwrap()[0].text, "Tom & Jerry <3");
+    }
+
+    #[test]
+    fn test_parse_ass() {
+        let contents = "[Script Info]\nTitle: test\n\n[V4+ Styles]\nFormat: Name, Fontname\nStyle: Default,Arial\n\n[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\nComment: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,comment\nDialogue: 0,0:00:01.50,0:00:03.05,Default,Tom,0,0,0,,{\\an8}Hello,\\Nworld\\hagain\nDialogue: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,First\n";
+        let subtitles = parse_ass(contents).unwrap();
+
+        assert_eq!(subtitles.len(), 2);
+        assert_eq!(subtitles[0].text, "First");
+        assert_eq!(subtitles[0].speaker, None);
+        assert_eq!(subtitles[1].start_timestamp, 1500);
+        assert_eq!(subtitles[1].end_timestamp, 3050);
+        assert_eq!(subtitles[1].text, "Hello,\nworld again");
+        assert_eq!(subtitles[1].speaker.as_deref(), Some("Tom"));
+    }
+
+    #[test]
+    fn test_ass_round_trip() {
+        let subtitles = vec![Subtitle {
+            index: 1,
+            start_timestamp: 3_723_456,
+            end_timestamp: 3_725_000,
+            text: "Hello, {world}\nagain".to_string(),
+            translation: Some("你好".to_string()),
+            speaker: Some("Speaker 1".to_string()),
+            ..Default::default()
+        }];
+
+        let config = AssConfig::default()
+            .with_font_name("Noto Sans")
+            .with_font_size(24)
+            .with_translation_font_size(18)
+            .with_enable_background(true)
+            .with_margin_v(20);
+
+        let contents = subtitles_to_ass(&subtitles, &config);
+        assert!(contents.contains("Style: Original,Noto Sans,24,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,3,1,0,2,10,10,20,1\n"));
+        assert!(contents.contains("Style: Translation,Noto Sans,18,"));
+        assert!(contents.contains("Dialogue: 0,1:02:03.45,1:02:05.00,Original,Speaker 1,0,0,0,,Hello, \\{world\\}\\Nagain\\N{\\rTranslation}你好\n"));
+
+        let parsed = parse_ass(&contents).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].start_timestamp, 3_723_450);
+        assert_eq!(parsed[0].text, "Hello, {world}\nagain");
+        assert_eq!(parsed[0].translation.as_deref(), Some("你好"));
+        assert_eq!(parsed[0].speaker.as_deref(), Some("Speaker 1"));
+    }
+
+    #[test]
+    fn test_exporters() {
+        let subtitles = vec![
+            Subtitle {
+                index: 1,
+                start_timestamp: 1000,
+                end_timestamp: 2500,
+                text: "Hello, \"Tom\" & <Jerry>".to_string(),
+                translation: Some("你好".to_string()),
+                words: vec![TranscriptionWord {
+                    text: "Hello".to_string(),
+                    start_time: 1000,
+                    end_time: 1500,
+                    probability: 0.9,
+                }],
+                speaker: Some("Speaker 1".to_string()),
+                confidence: Some(0.85),
+            },
+            Subtitle {
+                index: 2,
+                start_timestamp: 63_000,
+                end_timestamp: 64_000,
+                text: "Bye".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let export = |extension: &str| exporter(extension).unwrap().export(&subtitles).unwrap();
+
+        assert_eq!(
+            export("csv"),
+            "\u{feff}index,start,end,speaker,text,translation,confidence\r\n1,00:00:01.000,00:00:02.500,Speaker 1,\"Hello, \"\"Tom\"\" & <Jerry>\",你好,0.85\r\n2,00:01:03.000,00:01:04.000,,Bye,,\r\n"
+        );
+
+        let ttml = export("ttml");
+        assert!(ttml.contains("<ttm:agent xml:id=\"speaker_1\" type=\"person\">\n        <ttm:name type=\"full\">Speaker 1</ttm:name>"));
+        assert!(ttml.contains("<p begin=\"00:00:01.000\" end=\"00:00:02.500\" ttm:agent=\"speaker_1\">Hello, &quot;Tom&quot; &amp; &lt;Jerry&gt;<br/>你好</p>"));
+        assert!(ttml.contains("<p begin=\"00:01:03.000\" end=\"00:01:04.000\">Bye</p>"));
+
+        assert_eq!(
+            export("sbv"),
+            "0:00:01.000,0:00:02.500\nHello, \"Tom\" & <Jerry>\n你好\n\n0:01:03.000,0:01:04.000\nBye\n\n"
+        );
+        assert!(exporter_with_speaker_prefix("sbv", true)
+            .unwrap()
+            .export(&subtitles)
+            .unwrap()
+            .starts_with("0:00:01.000,0:00:02.500\nSpeaker 1: Hello"));
+
+        assert_eq!(
+            export("lrc"),
+            "[00:01.00]Hello, \"Tom\" & <Jerry> / 你好\n[00:02.50]\n[01:03.00]Bye\n[01:04.00]\n"
+        );
+
+        // the json file is lossless
+        let parsed = parse_json(&export("json")).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].text, subtitles[0].text);
+        assert_eq!(parsed[0].translation, subtitles[0].translation);
+        assert_eq!(parsed[0].speaker, subtitles[0].speaker);
+        assert_eq!(parsed[0].confidence, subtitles[0].confidence);
+        assert_eq!(parsed[0].words, subtitles[0].words);
+        assert_eq!(parsed[1].end_timestamp, 64_000);
+
+        assert!(exporter("docx").is_none());
+        for extension in EXPORTER_EXTENSIONS {
+            assert_eq!(exporter(extension).unwrap().extension(), extension);
+        }
+    }
+
+    #[test]
+    fn test_decode_subtitle_bytes() {
+        assert_eq!(decode_subtitle_bytes(b"\xEF\xBB\xBFhi"), "hi");
+        assert_eq!(decode_subtitle_bytes(&[0xFF, 0xFE, b'h', 0, b'i', 0]), "hi");
+        assert_eq!(parse_timestamp("1:02.5"), Some(62_500));
+        assert_eq!(parse_timestamp("00:61:00.000"), None);
+    }
+
     #[test]
     fn test_basic_split_with_timestamps() {
         let ((start1, end1, part1), (start2, end2, part2)) =
-            split_subtitle_into_two(0, 1000, "Hello, world!").unwrap();
+            split_subtitle_into_two(0, 1000, "Hello, world!", &[]).unwrap();
 
         assert_eq!(part1, "Hello,");
         assert_eq!(part2, "world!");
@@ -218,7 +1726,7 @@ mod tests {
     #[test]
     fn test_chinese_split_with_timestamps() {
         let ((start1, end1, part1), (start2, end2, part2)) =
-            split_subtitle_into_two(0, 1000, "你好，世界！").unwrap();
+            split_subtitle_into_two(0, 1000, "你好，世界！", &[]).unwrap();
 
         assert_eq!(part1, "你好，");
         assert_eq!(part2, "世界！");
@@ -230,7 +1738,7 @@ mod tests {
     #[test]
     fn test_no_delimiters_with_timestamps() {
         let ((start1, end1, part1), (_start2, end2, part2)) =
-            split_subtitle_into_two(0, 1000, "abcdefgh").unwrap();
+            split_subtitle_into_two(0, 1000, "abcdefgh", &[]).unwrap();
 
         assert_eq!(part1, "abcd");
         assert_eq!(part2, "efgh");
@@ -241,18 +1749,18 @@ mod tests {
 
     #[test]
     fn test_empty_string() {
-        assert!(split_subtitle_into_two(0, 1000, "").is_none());
+        assert!(split_subtitle_into_two(0, 1000, "", &[]).is_none());
     }
 
     #[test]
     fn test_single_character() {
-        assert!(split_subtitle_into_two(0, 1000, "a").is_none());
+        assert!(split_subtitle_into_two(0, 1000, "a", &[]).is_none());
     }
 
     #[test]
     fn test_time_calculation_proportion() {
         let ((_start1, end1, _part1), (start2, _end2, _part2)) =
-            split_subtitle_into_two(0, 1000, "Hello world").unwrap();
+            split_subtitle_into_two(0, 1000, "Hello world", &[]).unwrap();
 
         let total_chars = 11;
         let expected_split_time = (1000 * 5) / total_chars;
@@ -261,11 +1769,42 @@ mod tests {
         assert_eq!(start2, expected_split_time);
     }
 
+    #[test]
+    fn test_split_with_word_timestamps() {
+        let word = |text: &str, start_time, end_time| TranscriptionWord {
+            text: text.to_string(),
+            start_time,
+            end_time,
+            probability: 1.0,
+        };
+
+        let words = vec![
+            word("Hello,", 0, 200),
+            word("world", 700, 900),
+            word("again!", 900, 1000),
+        ];
+
+        let ((start1, end1, part1), (start2, end2, part2)) =
+            split_subtitle_into_two(0, 1000, "Hello, world again!", &words).unwrap();
+
+        assert_eq!(part1, "Hello,");
+        assert_eq!(part2, "world again!");
+        assert_eq!((start1, end1), (0, 200));
+        assert_eq!((start2, end2), (700, 1000));
+
+        // fallback to the proportional estimate if the content is edited
+        let ((_, end1, _), (start2, _, _)) =
+            split_subtitle_into_two(0, 1000, "Hello, world", &words).unwrap();
+
+        assert_eq!(end1, start2);
+        assert_eq!(end1, (1000 * 6) / 12);
+    }
+
     // cargo test test_complicate -- --no-capture
     #[test]
     fn test_complicate() {
         let s = "就來看下這個庫,的手用情況 就顯得是要支數是278次.給帶了兩個版本";
-        let items = split_subtitle_into_two(0, 100, s).unwrap();
+        let items = split_subtitle_into_two(0, 100, s, &[]).unwrap();
 
         println!("{items:?}");
     }
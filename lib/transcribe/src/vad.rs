@@ -1,6 +1,6 @@
 use super::ProgressStatus;
 use crate::wav;
-use anyhow::{bail, Result};
+use anyhow::Result;
 use std::{
     path::Path,
     sync::{
@@ -97,6 +97,35 @@ impl EnergyVAD {
         segments
     }
 
+    // Return the sample offset of the silent frame which is closest to the end.
+    // Use the quietest frame if no silent frame is found.
+    pub fn detect_cut_offset(&self, samples: &[f32]) -> Option<usize> {
+        let frame_size = ((self.sample_rate as u64 * self.frame_size_ms) as f32 / 1000.0) as usize;
+        let frame_shift =
+            ((self.sample_rate as u64 * self.frame_shift_ms) as f32 / 1000.0) as usize;
+
+        if frame_size == 0 || frame_shift == 0 || samples.len() < frame_size {
+            return None;
+        }
+
+        let frames = (0..=samples.len() - frame_size)
+            .step_by(frame_shift)
+            .map(|offset| {
+                (
+                    offset,
+                    self.calculate_rms(&samples[offset..offset + frame_size]),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let silent_frame = frames.iter().rev().find(|(_, rms)| *rms <= self.threshold);
+        let quietest_frame = frames.iter().min_by(|a, b| a.1.total_cmp(&b.1));
+
+        silent_frame
+            .or(quietest_frame)
+            .map(|(offset, _)| offset + frame_size / 2)
+    }
+
     fn detect_silent_offset_ms(&self, samples: &[f32]) -> u64 {
         let frame_size = ((self.sample_rate as u64 * self.frame_size_ms) as f32 / 1000.0) as usize;
         let frame_shift =
@@ -133,19 +162,9 @@ pub fn trim_start_slient_duration_of_audio(
 ) -> Result<(Vec<(u64, u64)>, ProgressStatus)> {
     let audio_data = wav::read_file(&audio_path)?;
 
-    let audio_samples = if !audio_data.is_whisper_compatible() {
-        if audio_data.config.sample_rate != 16000 {
-            bail!(
-                "Not compatible with whisper. Actual sample rate {}, expect 16kHz",
-                audio_data.config.sample_rate
-            );
-        }
-
-        if audio_data.config.channels > 1 {
-            audio_data.to_mono().samples
-        } else {
-            audio_data.samples.clone()
-        }
+    // the energy VAD works in any sample rate
+    let audio_samples = if audio_data.config.channels > 1 {
+        audio_data.to_mono().samples
     } else {
         audio_data.samples.clone()
     };
@@ -222,6 +241,22 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_vad_detect_cut_offset() {
+        let sample_rate = 1000;
+        let mut samples = vec![0.5; 3000];
+        samples[1000..1500].fill(0.005);
+        samples[2000..2500].fill(0.01);
+
+        let vad = EnergyVAD::new(sample_rate).with_threshold(0.05);
+        assert_eq!(vad.detect_cut_offset(&samples), Some(2400));
+
+        let vad = EnergyVAD::new(sample_rate).with_threshold(0.001);
+        assert_eq!(vad.detect_cut_offset(&samples), Some(1100));
+
+        assert_eq!(vad.detect_cut_offset(&samples[..100]), None);
+    }
+
     // cargo test test_trim_start_slient_duration_of_audio -- --no-capture
     #[test]
     fn test_trim_start_slient_duration_of_audio() -> Result<()> {
@@ -2,7 +2,12 @@ use anyhow::{anyhow, bail, Result};
 use hound::{SampleFormat, WavReader};
 use log::debug;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::{f64::consts::PI, fs::File, io::BufReader, path::Path};
+
+pub const WHISPER_SAMPLE_RATE: u32 = 16000;
+
+const RESAMPLE_ZERO_CROSSINGS: usize = 16; // of the sinc on each side
+const RESAMPLE_ROLLOFF: f64 = 0.945; // of the Nyquist frequency, to leave room for the transition band
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioConfig {
@@ -14,7 +19,7 @@ pub struct AudioConfig {
 impl Default for AudioConfig {
     fn default() -> Self {
         Self {
-            sample_rate: 16000,
+            sample_rate: WHISPER_SAMPLE_RATE,
             channels: 1,
             bit_depth: 16,
         }
@@ -35,7 +40,7 @@ impl AudioConfig {
     }
 
     pub fn is_whisper_compatible(&self) -> bool {
-        self.sample_rate == 16000 && self.channels == 1 && self.bit_depth == 16
+        self.sample_rate == WHISPER_SAMPLE_RATE && self.channels == 1 && self.bit_depth == 16
     }
 }
 
@@ -115,48 +120,283 @@ impl AudioData {
     pub fn is_whisper_compatible(&self) -> bool {
         self.config.is_whisper_compatible()
     }
+
+    pub fn resample(&self, target_rate: u32) -> AudioData {
+        if self.config.sample_rate == target_rate {
+            return self.clone();
+        }
+
+        let channels = self.config.channels.max(1) as usize;
+        let resampled_channels = (0..channels)
+            .map(|channel| {
+                let samples = self
+                    .samples
+                    .iter()
+                    .skip(channel)
+                    .step_by(channels)
+                    .copied()
+                    .collect::<Vec<_>>();
+
+                let mut resampler = Resampler::new(self.config.sample_rate, target_rate);
+                let mut output = resampler.process(&samples);
+                output.extend(resampler.flush());
+                output
+            })
+            .collect::<Vec<_>>();
+
+        let frame_count = resampled_channels[0].len();
+        let mut samples = Vec::with_capacity(frame_count * channels);
+        for frame in 0..frame_count {
+            for channel in resampled_channels.iter() {
+                samples.push(channel[frame]);
+            }
+        }
+
+        debug!(
+            "Resampled audio: {}Hz -> {target_rate}Hz",
+            self.config.sample_rate
+        );
+
+        AudioData::new(
+            samples,
+            AudioConfig {
+                sample_rate: target_rate,
+                ..self.config
+            },
+        )
+    }
+}
+
+// Polyphase windowed-sinc resampler of a mono stream. The input could be fed in
+// pieces, and the output is the same as resampling the whole input at once.
+pub struct Resampler {
+    up: usize,   // the target rate divided by the gcd of the rates
+    down: usize, // the source rate divided by the gcd of the rates
+    half_taps: usize,
+    kernels: Vec<Vec<f32>>, // the filter of each phase, `half_taps * 2 + 1` taps
+
+    buffer: Vec<f32>, // the history and the pending input
+    position: usize,  // the input index of the next output in the buffer
+    phase: usize,
+    input_count: u64,
+    output_count: u64,
+}
+
+impl Resampler {
+    pub fn new(source_rate: u32, target_rate: u32) -> Self {
+        let (source_rate, target_rate) = (source_rate.max(1) as usize, target_rate.max(1) as usize);
+        let divisor = gcd(source_rate, target_rate);
+        let (up, down) = (target_rate / divisor, source_rate / divisor);
+
+        // the cutoff frequency relative to the source Nyquist frequency,
+        // it's lower than the target Nyquist frequency in downsampling
+        let cutoff = RESAMPLE_ROLLOFF * (up as f64 / down as f64).min(1.0);
+        let half_taps = (RESAMPLE_ZERO_CROSSINGS as f64 / cutoff).ceil() as usize;
+
+        let kernels = if up == down {
+            vec![]
+        } else {
+            (0..up)
+                .map(|phase| {
+                    let taps = (0..=half_taps * 2)
+                        .map(|k| {
+                            let distance = half_taps as f64 - k as f64 + phase as f64 / up as f64;
+                            let x = cutoff * distance;
+                            let sinc = if x == 0.0 {
+                                1.0
+                            } else {
+                                (PI * x).sin() / (PI * x)
+                            };
+                            cutoff * sinc * blackman_window(distance / (half_taps + 1) as f64)
+                        })
+                        .collect::<Vec<_>>();
+
+                    // unity gain at DC for every phase
+                    let sum = taps.iter().sum::<f64>();
+                    taps.into_iter().map(|tap| (tap / sum) as f32).collect()
+                })
+                .collect()
+        };
+
+        Self {
+            up,
+            down,
+            half_taps,
+            kernels,
+            buffer: vec![0.0; half_taps],
+            position: half_taps,
+            phase: 0,
+            input_count: 0,
+            output_count: 0,
+        }
+    }
+
+    // The output lags behind the input by the half length of the filter,
+    // and it's returned after the following input or the `flush`
+    pub fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        if self.up == self.down {
+            return input.to_vec();
+        }
+
+        self.input_count += input.len() as u64;
+        self.buffer.extend_from_slice(input);
+        self.resample(u64::MAX)
+    }
+
+    // Return the rest of the output at the end of the stream
+    pub fn flush(&mut self) -> Vec<f32> {
+        if self.up == self.down {
+            return vec![];
+        }
+
+        let expected_count = (self.input_count * self.up as u64).div_ceil(self.down as u64);
+        self.buffer
+            .extend(std::iter::repeat_n(0.0, self.half_taps + 1));
+        self.resample(expected_count)
+    }
+
+    fn resample(&mut self, max_output_count: u64) -> Vec<f32> {
+        let mut output = vec![];
+
+        while self.position + self.half_taps < self.buffer.len()
+            && self.output_count < max_output_count
+        {
+            let window =
+                &self.buffer[self.position - self.half_taps..=self.position + self.half_taps];
+            let sample = window
+                .iter()
+                .zip(self.kernels[self.phase].iter())
+                .map(|(x, h)| x * h)
+                .sum::<f32>();
+            output.push(sample);
+            self.output_count += 1;
+
+            self.phase += self.down;
+            self.position += self.phase / self.up;
+            self.phase %= self.up;
+        }
+
+        // keep the history of the next output
+        let consumed = self.position.min(self.buffer.len()) - self.half_taps;
+        self.buffer.drain(..consumed);
+        self.position -= consumed;
+
+        output
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// `x` is in [-1, 1]
+fn blackman_window(x: f64) -> f64 {
+    0.42 + 0.5 * (PI * x).cos() + 0.08 * (2.0 * PI * x).cos()
 }
 
 pub fn read_file<P: AsRef<Path>>(path: P) -> Result<AudioData> {
-    let path = path.as_ref();
-    if !path.exists() {
-        bail!("file not found {}", path.display());
+    let mut reader = WavChunkReader::open(path)?;
+    reader.read_frames(reader.frame_count())
+}
+
+// Read the wav file in windows to avoid loading the whole file into memory
+pub struct WavChunkReader {
+    reader: WavReader<BufReader<File>>,
+    config: AudioConfig,
+    position: usize, // frame
+}
+
+impl WavChunkReader {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            bail!("file not found {}", path.display());
+        }
+
+        let reader = WavReader::open(path).map_err(|e| anyhow!("open wav file failed: {e}"))?;
+
+        let spec = reader.spec();
+        let config = AudioConfig {
+            sample_rate: spec.sample_rate,
+            channels: spec.channels,
+            bit_depth: spec.bits_per_sample,
+        };
+
+        Ok(Self {
+            reader,
+            config,
+            position: 0,
+        })
     }
 
-    let mut reader = WavReader::open(path).map_err(|e| anyhow!("open wav file failed: {e}"))?;
+    pub fn config(&self) -> &AudioConfig {
+        &self.config
+    }
 
-    let spec = reader.spec();
-    let config = AudioConfig {
-        sample_rate: spec.sample_rate,
-        channels: spec.channels,
-        bit_depth: spec.bits_per_sample,
-    };
-
-    let samples: Vec<f32> = match spec.sample_format {
-        SampleFormat::Float => reader
-            .samples::<f32>()
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| anyhow!("Read float point sample failed: {e}"))?,
-
-        SampleFormat::Int => {
-            let int_samples: Result<Vec<i32>, _> = reader.samples::<i32>().collect();
-            match int_samples {
-                Ok(samples) => {
-                    // Convert floating point according to bit depth
-                    let max_value = match spec.bits_per_sample {
-                        16 => i16::MAX as f32,
-                        24 => 8388607.0, // 2^23 - 1
-                        32 => i32::MAX as f32,
-                        _ => bail!("Unsupported bits per sample: {}", spec.bits_per_sample),
-                    };
-                    samples.into_iter().map(|x| x as f32 / max_value).collect()
-                }
-                Err(e) => bail!("Read file sample failed: {e}"),
+    pub fn frame_count(&self) -> usize {
+        self.reader.duration() as usize
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.frame_count() as f64 / self.config.sample_rate as f64
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn remaining_frames(&self) -> usize {
+        self.frame_count().saturating_sub(self.position)
+    }
+
+    pub fn seek(&mut self, frame: usize) -> Result<()> {
+        let frame = frame.min(self.frame_count());
+        self.reader
+            .seek(frame as u32)
+            .map_err(|e| anyhow!("Seek wav file failed: {e}"))?;
+
+        self.position = frame;
+        Ok(())
+    }
+
+    // Return less frames than `frame_count` at the end of the file
+    pub fn read_frames(&mut self, frame_count: usize) -> Result<AudioData> {
+        let spec = self.reader.spec();
+        let sample_count = frame_count.saturating_mul(spec.channels as usize);
+
+        let samples: Vec<f32> = match spec.sample_format {
+            SampleFormat::Float => self
+                .reader
+                .samples::<f32>()
+                .take(sample_count)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| anyhow!("Read float point sample failed: {e}"))?,
+
+            SampleFormat::Int => {
+                // Convert floating point according to bit depth
+                let max_value = match spec.bits_per_sample {
+                    16 => i16::MAX as f32,
+                    24 => 8388607.0, // 2^23 - 1
+                    32 => i32::MAX as f32,
+                    _ => bail!("Unsupported bits per sample: {}", spec.bits_per_sample),
+                };
+
+                self.reader
+                    .samples::<i32>()
+                    .take(sample_count)
+                    .map(|x| x.map(|x| x as f32 / max_value))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| anyhow!("Read file sample failed: {e}"))?
             }
-        }
-    };
+        };
 
-    Ok(AudioData::new(samples, config))
+        self.position += samples.len() / spec.channels.max(1) as usize;
+        Ok(AudioData::new(samples, self.config.clone()))
+    }
 }
 
 pub fn is_whisper_compatible(path: impl AsRef<Path>) -> Result<()> {
@@ -164,9 +404,9 @@ pub fn is_whisper_compatible(path: impl AsRef<Path>) -> Result<()> {
         .map_err(|e| anyhow!("Failed to open {}. Error: {e}", path.as_ref().display()))?;
     let spec = reader.spec();
 
-    if spec.sample_rate != 16000 {
+    if spec.sample_rate != WHISPER_SAMPLE_RATE {
         bail!(
-            "Sample rate mismatch. Expected: 16000, actual: {}",
+            "Sample rate mismatch. Expected: {WHISPER_SAMPLE_RATE}, actual: {}",
             spec.sample_rate
         );
     }
@@ -184,3 +424,95 @@ pub fn is_whisper_compatible(path: impl AsRef<Path>) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frequency: f64, sample_rate: u32, seconds: f64) -> Vec<f32> {
+        (0..(sample_rate as f64 * seconds) as usize)
+            .map(|i| (2.0 * PI * frequency * i as f64 / sample_rate as f64).sin() as f32 * 0.5)
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_resample() {
+        for source_rate in [8000, 22050, 44100, 48000] {
+            let audio_data = AudioData::new(
+                sine(440.0, source_rate, 1.0),
+                AudioConfig::new(source_rate, 1, 16),
+            );
+
+            let resampled = audio_data.resample(WHISPER_SAMPLE_RATE);
+            assert_eq!(resampled.config.sample_rate, WHISPER_SAMPLE_RATE);
+            assert_eq!(resampled.samples.len(), WHISPER_SAMPLE_RATE as usize);
+
+            // compare with the sine generated at the target rate, skip the edges
+            let expected = sine(440.0, WHISPER_SAMPLE_RATE, 1.0);
+            let error = resampled.samples[1000..15000]
+                .iter()
+                .zip(&expected[1000..15000])
+                .map(|(a, b)| (a - b).abs())
+                .fold(0.0f32, f32::max);
+            assert!(error < 0.01, "{source_rate}Hz error: {error}");
+        }
+    }
+
+    #[test]
+    fn test_resample_anti_aliasing() {
+        // it would be aliased to 6kHz without the low-pass filter
+        let audio_data = AudioData::new(sine(10000.0, 48000, 1.0), AudioConfig::new(48000, 1, 16));
+        let resampled = audio_data.resample(WHISPER_SAMPLE_RATE);
+
+        assert!(rms(&resampled.samples[1000..15000]) < 0.005);
+    }
+
+    #[test]
+    fn test_resample_stereo() {
+        let (left, right) = (sine(300.0, 44100, 0.5), sine(600.0, 44100, 0.5));
+        let samples = left
+            .iter()
+            .zip(right.iter())
+            .flat_map(|(l, r)| [*l, *r])
+            .collect::<Vec<_>>();
+
+        let resampled = AudioData::new(samples, AudioConfig::new(44100, 2, 16)).resample(16000);
+        assert_eq!(resampled.config.channels, 2);
+        assert_eq!(resampled.frame_count(), 8000);
+
+        for (channel, samples) in [left, right].into_iter().enumerate() {
+            let expected = AudioData::new(samples, AudioConfig::new(44100, 1, 16)).resample(16000);
+            let output = resampled
+                .samples
+                .iter()
+                .skip(channel)
+                .step_by(2)
+                .copied()
+                .collect::<Vec<_>>();
+            assert_eq!(output, expected.samples);
+        }
+    }
+
+    #[test]
+    fn test_resampler_streaming() {
+        let samples = sine(440.0, 44100, 1.0);
+
+        let mut resampler = Resampler::new(44100, 16000);
+        let mut expected = resampler.process(&samples);
+        expected.extend(resampler.flush());
+
+        let mut resampler = Resampler::new(44100, 16000);
+        let mut output = vec![];
+        for chunk in samples.chunks(1234) {
+            output.extend(resampler.process(chunk));
+        }
+        output.extend(resampler.flush());
+        assert!(resampler.flush().is_empty());
+
+        assert_eq!(output, expected);
+    }
+}
@@ -1,29 +1,149 @@
-use super::wav::{self, AudioData};
+#[cfg(feature = "symphonia")]
+use super::decoder;
+use super::{
+    diarization::{DiarizationMethod, Diarizer, DEFAULT_MAX_SPEAKERS},
+    glossary::Glossary,
+    hallucination::HallucinationReason,
+    hotword::{self, HotwordBias},
+    model_cache,
+    vad::EnergyVAD,
+    wav::{self, AudioConfig, AudioData, Resampler, WavChunkReader},
+};
 use anyhow::{anyhow, bail, Context, Result};
-use log::debug;
+use log::{debug, warn};
 use serde::{Deserialize, Serialize};
 use std::{
+    cell::RefCell,
+    ffi::{c_int, c_void, CStr},
     fs,
     path::{Path, PathBuf},
+    rc::Rc,
+    slice,
     sync::{atomic::AtomicBool, Arc},
 };
 use whisper_rs::{
-    FullParams, SamplingStrategy, SegmentCallbackData, WhisperContext, WhisperContextParameters,
-    WhisperState, WhisperVadParams,
+    whisper_rs_sys, DtwMode, DtwModelPreset, DtwParameters, FullParams, WhisperContext,
+    WhisperContextParameters, WhisperState, WhisperSysContext, WhisperSysState, WhisperTokenId,
+    WhisperVadParams,
 };
 
 const GGML_SILERO_VAD_MODEL: &'static [u8] = include_bytes!("../data/ggml-silero-v5.1.2.bin");
 
+const MIN_CHUNK_LENGTH: u32 = 30; // seconds
+const CHUNK_CUT_SEARCH_LENGTH: usize = 10; // seconds
+const CHUNK_SILENCE_THRESHOLD: f32 = 0.01;
+const CHUNK_PROMPT_SEGMENTS: usize = 3;
+const LANGUAGE_DETECT_LENGTH: usize = 30; // seconds, the window of whisper
+const LANGUAGE_DETECT_TOP_N: usize = 5;
+const MIXED_LANGUAGE_CHUNK_LENGTH: usize = 10; // seconds
+
+// built with any of the GPU backends of whisper-rs
+pub const GPU_BACKEND_ENABLED: bool = cfg!(any(
+    feature = "cuda",
+    feature = "hipblas",
+    feature = "metal",
+    feature = "vulkan"
+));
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SamplingStrategy {
+    Greedy { best_of: i32 },
+
+    // whisper.cpp treats `patience` -1.0 as the default value
+    BeamSearch { beam_size: i32, patience: f32 },
+}
+
+impl Default for SamplingStrategy {
+    fn default() -> Self {
+        SamplingStrategy::Greedy { best_of: 1 }
+    }
+}
+
+impl From<&SamplingStrategy> for whisper_rs::SamplingStrategy {
+    fn from(strategy: &SamplingStrategy) -> Self {
+        match *strategy {
+            SamplingStrategy::Greedy { best_of } => {
+                whisper_rs::SamplingStrategy::Greedy { best_of }
+            }
+            SamplingStrategy::BeamSearch {
+                beam_size,
+                patience,
+            } => whisper_rs::SamplingStrategy::BeamSearch {
+                beam_size,
+                patience,
+            },
+        }
+    }
+}
+
+// The alignment heads of the DTW token timestamps. The model preset should
+// match the model, and `TopMost` uses the top most text layers of any model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DtwPreset {
+    TinyEn,
+    Tiny,
+    BaseEn,
+    Base,
+    SmallEn,
+    Small,
+    MediumEn,
+    Medium,
+    LargeV1,
+    LargeV2,
+    LargeV3,
+    LargeV3Turbo,
+    TopMost { n_top: i32 },
+}
+
+impl From<&DtwPreset> for DtwMode<'static> {
+    fn from(preset: &DtwPreset) -> Self {
+        let model_preset = match *preset {
+            DtwPreset::TopMost { n_top } => return DtwMode::TopMost { n_top },
+            DtwPreset::TinyEn => DtwModelPreset::TinyEn,
+            DtwPreset::Tiny => DtwModelPreset::Tiny,
+            DtwPreset::BaseEn => DtwModelPreset::BaseEn,
+            DtwPreset::Base => DtwModelPreset::Base,
+            DtwPreset::SmallEn => DtwModelPreset::SmallEn,
+            DtwPreset::Small => DtwModelPreset::Small,
+            DtwPreset::MediumEn => DtwModelPreset::MediumEn,
+            DtwPreset::Medium => DtwModelPreset::Medium,
+            DtwPreset::LargeV1 => DtwModelPreset::LargeV1,
+            DtwPreset::LargeV2 => DtwModelPreset::LargeV2,
+            DtwPreset::LargeV3 => DtwModelPreset::LargeV3,
+            DtwPreset::LargeV3Turbo => DtwModelPreset::LargeV3Turbo,
+        };
+
+        DtwMode::ModelPreset { model_preset }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct WhisperConfig {
     pub model_path: PathBuf,
     pub vad_model_path: Option<PathBuf>,
+    pub use_gpu: bool, // it falls back to the CPU without a GPU backend feature
+    pub flash_attn: bool,
+    pub gpu_device: i32,
+    pub dtw: Option<DtwPreset>, // None is the token timestamps without DTW
     pub language: Option<String>, // "zh", "en"，None is auto detect
     pub translate: bool,
     pub n_threads: i32,
     pub temperature: f32,
-    pub max_segment_length: Option<u32>,
+    pub max_segment_length: Option<u32>, // characters, None is no limit
+    pub split_on_word: bool,             // split segments at word boundaries instead of tokens
     pub initial_prompt: Option<String>,
+    pub glossary: Vec<String>, // the domain terms, they are in the prompt and fix the misspellings
+    pub hotwords: Vec<(String, f32)>, // (word, logit bias), the words are boosted in decoding
+    pub sampling_strategy: SamplingStrategy,
+    pub entropy_threshold: f32,
+    pub logprob_threshold: f32,
+    pub no_speech_threshold: f32,
+    pub suppress_blank: bool,
+    pub mixed_language: Option<Vec<String>>, // the candidate languages of the chunks, empty is any
+    pub diarization: Option<DiarizationMethod>, // None is no speaker labels
+    pub max_speakers: usize,
+    pub chunk_length: Option<u32>, // seconds, None is transcribing the whole audio at once
+    pub time_range: Option<(u64, u64)>, // (start_ms, end_ms), end_ms is clamped to the audio duration
     pub debug_mode: bool,
 }
 
@@ -32,12 +152,29 @@ impl Default for WhisperConfig {
         Self {
             model_path: PathBuf::from("models/ggml-base.bin"),
             vad_model_path: None,
+            use_gpu: GPU_BACKEND_ENABLED,
+            flash_attn: false,
+            gpu_device: 0,
+            dtw: None,
             language: None,
             translate: false,
             n_threads: num_cpus::get().min(8) as i32,
             temperature: 0.0,
             max_segment_length: None,
+            split_on_word: false,
             initial_prompt: None,
+            glossary: vec![],
+            hotwords: vec![],
+            sampling_strategy: SamplingStrategy::default(),
+            entropy_threshold: 2.4,
+            logprob_threshold: -1.0,
+            no_speech_threshold: 0.6,
+            suppress_blank: true,
+            mixed_language: None,
+            diarization: None,
+            max_speakers: DEFAULT_MAX_SPEAKERS,
+            chunk_length: None,
+            time_range: None,
             debug_mode: false,
         }
     }
@@ -56,6 +193,26 @@ impl WhisperConfig {
         self
     }
 
+    pub fn with_use_gpu(mut self, use_gpu: bool) -> Self {
+        self.use_gpu = use_gpu;
+        self
+    }
+
+    pub fn with_flash_attn(mut self, flash_attn: bool) -> Self {
+        self.flash_attn = flash_attn;
+        self
+    }
+
+    pub fn with_gpu_device(mut self, gpu_device: i32) -> Self {
+        self.gpu_device = gpu_device;
+        self
+    }
+
+    pub fn with_dtw(mut self, preset: DtwPreset) -> Self {
+        self.dtw = Some(preset);
+        self
+    }
+
     pub fn with_language<S: Into<String>>(mut self, language: S) -> Self {
         self.language = Some(language.into());
         self
@@ -76,16 +233,110 @@ impl WhisperConfig {
         self
     }
 
+    pub fn with_max_segment_length(mut self, length: u32) -> Self {
+        self.max_segment_length = Some(length);
+        self
+    }
+
+    pub fn with_split_on_word(mut self, split_on_word: bool) -> Self {
+        self.split_on_word = split_on_word;
+        self
+    }
+
     pub fn with_initial_prompt<S: Into<String>>(mut self, prompt: S) -> Self {
         self.initial_prompt = Some(prompt.into());
         self
     }
 
+    pub fn with_glossary<S: Into<String>>(mut self, terms: impl IntoIterator<Item = S>) -> Self {
+        self.glossary = terms.into_iter().map(|term| term.into()).collect();
+        self
+    }
+
+    pub fn with_hotwords<S: Into<String>>(
+        mut self,
+        hotwords: impl IntoIterator<Item = (S, f32)>,
+    ) -> Self {
+        self.hotwords = hotwords
+            .into_iter()
+            .map(|(word, bias)| (word.into(), bias))
+            .collect();
+        self
+    }
+
+    pub fn with_sampling_strategy(mut self, strategy: SamplingStrategy) -> Self {
+        self.sampling_strategy = strategy;
+        self
+    }
+
+    pub fn with_entropy_threshold(mut self, threshold: f32) -> Self {
+        self.entropy_threshold = threshold;
+        self
+    }
+
+    pub fn with_logprob_threshold(mut self, threshold: f32) -> Self {
+        self.logprob_threshold = threshold;
+        self
+    }
+
+    pub fn with_no_speech_threshold(mut self, threshold: f32) -> Self {
+        self.no_speech_threshold = threshold;
+        self
+    }
+
+    pub fn with_suppress_blank(mut self, suppress_blank: bool) -> Self {
+        self.suppress_blank = suppress_blank;
+        self
+    }
+
+    // Detect the language chunk by chunk, and each chunk is transcribed in its own
+    // language. It's for the recordings switching between languages.
+    pub fn with_mixed_language<S: Into<String>>(
+        mut self,
+        candidates: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.mixed_language = Some(candidates.into_iter().map(|lang| lang.into()).collect());
+        self
+    }
+
+    pub fn with_diarization(mut self, method: DiarizationMethod, max_speakers: usize) -> Self {
+        self.diarization = Some(method);
+        self.max_speakers = max_speakers;
+        self
+    }
+
+    pub fn with_chunk_length(mut self, seconds: u32) -> Self {
+        self.chunk_length = Some(seconds);
+        self
+    }
+
+    pub fn with_time_range(mut self, start_ms: u64, end_ms: u64) -> Self {
+        self.time_range = Some((start_ms, end_ms));
+        self
+    }
+
     pub fn with_debug_mode(mut self, debug_mode: bool) -> Self {
         self.debug_mode = debug_mode;
         self
     }
 
+    pub fn context_params(&self) -> WhisperContextParameters<'static> {
+        let mut params = WhisperContextParameters::default();
+        params
+            .use_gpu(self.use_gpu)
+            .flash_attn(self.flash_attn)
+            .gpu_device(self.gpu_device);
+
+        if let Some(preset) = &self.dtw {
+            params.dtw_parameters(DtwParameters {
+                mode: preset.into(),
+                ..Default::default()
+            });
+        }
+
+        params
+    }
+
     pub fn validate(&self) -> Result<()> {
         if !self.model_path.exists() {
             bail!("model path not exist: {}", self.model_path.display());
@@ -95,10 +346,67 @@ impl WhisperConfig {
             bail!("n_threads is 0");
         }
 
+        if self.gpu_device < 0 {
+            bail!("gpu_device should be at least 0");
+        }
+
+        // whisper.cpp disables DTW when flash attention is enabled
+        if self.flash_attn && self.dtw.is_some() {
+            bail!("DTW token timestamps don't work with flash attention");
+        }
+
+        if matches!(self.dtw, Some(DtwPreset::TopMost { n_top }) if n_top <= 0) {
+            bail!("n_top of DTW should be greater than 0");
+        }
+
         if !(0.0..=1.0).contains(&self.temperature) {
             bail!("temperature should between 0.0 and 1.0");
         }
 
+        if self.max_segment_length == Some(0) {
+            bail!("max_segment_length should be greater than 0");
+        }
+
+        if !(0.0..=1.0).contains(&self.no_speech_threshold) {
+            bail!("no_speech_threshold should between 0.0 and 1.0");
+        }
+
+        if let Some(lang) = self
+            .mixed_language
+            .iter()
+            .flatten()
+            .find(|lang| whisper_rs::get_lang_id(lang).is_none())
+        {
+            bail!("unknown language of the mixed language mode: {lang}");
+        }
+
+        hotword::validate(&self.hotwords)?;
+
+        if self.diarization.is_some() && self.max_speakers == 0 {
+            bail!("max_speakers should be greater than 0");
+        }
+
+        match self.sampling_strategy {
+            SamplingStrategy::Greedy { best_of } if best_of <= 0 => {
+                bail!("best_of should be greater than 0");
+            }
+            SamplingStrategy::BeamSearch { beam_size, .. } if beam_size <= 0 => {
+                bail!("beam_size should be greater than 0");
+            }
+            _ => (),
+        }
+
+        if self.chunk_length.is_some_and(|x| x < MIN_CHUNK_LENGTH) {
+            bail!("chunk_length should be at least {MIN_CHUNK_LENGTH} seconds");
+        }
+
+        if self
+            .time_range
+            .is_some_and(|(start_ms, end_ms)| start_ms >= end_ms)
+        {
+            bail!("the start of time range should be less than the end");
+        }
+
         Ok(())
     }
 }
@@ -110,12 +418,50 @@ pub struct TranscriptionSegment {
     pub end_time: u64,   // ms
     pub text: String,
     pub confidence: f32, // (0.0-1.0)
+
+    #[serde(default)]
+    pub words: Vec<TranscriptionWord>,
+
+    #[serde(default)]
+    pub speaker: Option<String>,
+
+    #[serde(default)]
+    pub language: Option<String>,
+
+    // the reasons of the likely hallucination, empty is not flagged
+    #[serde(default)]
+    pub hallucination: Vec<HallucinationReason>,
+
+    // tinydiarize predicts that the next segment is spoken by another speaker
+    #[serde(default)]
+    pub speaker_turn: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptionWord {
+    pub text: String,
+    pub start_time: u64,  // ms
+    pub end_time: u64,    // ms
+    pub probability: f32, // (0.0-1.0)
+}
+
+#[derive(Debug, Clone, Default)]
+struct TokenTiming {
+    bytes: Vec<u8>,
+    start_time: u64, // ms
+    end_time: u64,   // ms
+    probability: f32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TranscriptionResult {
     pub text: String,
     pub language: Option<String>,
+
+    // (language, probability) in descending order, it's only detected in the auto mode
+    #[serde(default)]
+    pub language_probabilities: Vec<(String, f32)>,
+
     pub segments: Vec<TranscriptionSegment>,
     pub processing_time: u64, // ms
     pub audio_duration: u64,  // ms
@@ -155,6 +501,7 @@ impl TranscriptionResult {
         TranscriptionResult {
             text: filtered_text,
             language: self.language.clone(),
+            language_probabilities: self.language_probabilities.clone(),
             segments: filtered_segments,
             processing_time: self.processing_time,
             audio_duration: self.audio_duration,
@@ -165,24 +512,21 @@ impl TranscriptionResult {
 pub struct WhisperTranscriber {
     context: Arc<WhisperContext>,
     config: WhisperConfig,
+    hotwords: Option<HotwordBias>, // the tokens depend on the model
 }
 
 impl WhisperTranscriber {
     pub fn new(config: WhisperConfig) -> Result<Self> {
         config.validate()?;
 
-        debug!("Load Whisper model: {}", config.model_path.display());
-
-        let ctx_params = WhisperContextParameters::default();
-        let context = WhisperContext::new_with_params(
-            config.model_path.to_string_lossy().as_ref(),
-            ctx_params,
-        )
-        .map_err(|e| anyhow!("Load Whisper model error: {e}"))?;
+        let ctx_params = config.context_params();
+        let context = model_cache::load_whisper_model(&config.model_path, ctx_params)?;
+        let hotwords = HotwordBias::new(&context, &config.hotwords)?;
 
         Ok(Self {
-            context: Arc::new(context),
+            context,
             config,
+            hotwords,
         })
     }
 
@@ -190,22 +534,81 @@ impl WhisperTranscriber {
         &self,
         audio_path: P,
         progress_cb: impl FnMut(i32) + 'static,
-        segmemnt_cb: impl FnMut(SegmentCallbackData) + 'static,
+        segmemnt_cb: impl FnMut(TranscriptionSegment) + 'static,
         abort_cb: impl FnMut() -> bool + 'static,
     ) -> Result<TranscriptionResult> {
-        is_valid_aduio_file(&audio_path)?;
+        is_supported_audio_file(&audio_path)?;
         debug!("Start transcribe: {}", audio_path.as_ref().display());
 
+        #[cfg(feature = "symphonia")]
+        if let Some(decoded_file) = DecodedWavFile::new(audio_path.as_ref())? {
+            return self
+                .transcribe_wav_file(&decoded_file.path, progress_cb, segmemnt_cb, abort_cb)
+                .await;
+        }
+
+        self.transcribe_wav_file(audio_path, progress_cb, segmemnt_cb, abort_cb)
+            .await
+    }
+
+    async fn transcribe_wav_file<P: AsRef<Path>>(
+        &self,
+        audio_path: P,
+        progress_cb: impl FnMut(i32) + 'static,
+        segmemnt_cb: impl FnMut(TranscriptionSegment) + 'static,
+        abort_cb: impl FnMut() -> bool + 'static,
+    ) -> Result<TranscriptionResult> {
+        if self.config.chunk_length.is_some() || self.config.mixed_language.is_some() {
+            return self.transcribe_file_in_chunks(
+                &audio_path,
+                0,
+                progress_cb,
+                segmemnt_cb,
+                abort_cb,
+            );
+        }
+
         let audio_data = wav::read_file(&audio_path)?;
         self.transcribe_audio_data(&audio_data, progress_cb, segmemnt_cb, abort_cb)
             .await
     }
 
+    // Continue transcribing from `offset_ms` of the audio file. Only the new
+    // segments are returned, and their timestamps are relative to the file start.
+    pub async fn resume_transcribe_file<P: AsRef<Path>>(
+        &self,
+        audio_path: P,
+        offset_ms: u64,
+        progress_cb: impl FnMut(i32) + 'static,
+        segmemnt_cb: impl FnMut(TranscriptionSegment) + 'static,
+        abort_cb: impl FnMut() -> bool + 'static,
+    ) -> Result<TranscriptionResult> {
+        is_supported_audio_file(&audio_path)?;
+        debug!(
+            "Resume transcribe: {} from {:.2}s",
+            audio_path.as_ref().display(),
+            offset_ms as f64 / 1000.0
+        );
+
+        #[cfg(feature = "symphonia")]
+        if let Some(decoded_file) = DecodedWavFile::new(audio_path.as_ref())? {
+            return self.transcribe_file_in_chunks(
+                &decoded_file.path,
+                offset_ms,
+                progress_cb,
+                segmemnt_cb,
+                abort_cb,
+            );
+        }
+
+        self.transcribe_file_in_chunks(&audio_path, offset_ms, progress_cb, segmemnt_cb, abort_cb)
+    }
+
     pub async fn transcribe_audio_data(
         &self,
         audio_data: &AudioData,
         progress_cb: impl FnMut(i32) + 'static,
-        segmemnt_cb: impl FnMut(SegmentCallbackData) + 'static,
+        mut segmemnt_cb: impl FnMut(TranscriptionSegment) + 'static,
         abort_cb: impl FnMut() -> bool + 'static,
     ) -> Result<TranscriptionResult> {
         let start_time = std::time::Instant::now();
@@ -221,22 +624,432 @@ impl WhisperTranscriber {
             audio_data.duration()
         );
 
+        if self.config.mixed_language.is_some() {
+            return self.transcribe_mixed_language_samples(
+                &audio_samples,
+                progress_cb,
+                segmemnt_cb,
+                abort_cb,
+            );
+        }
+
+        let mut state = self
+            .context
+            .create_state()
+            .map_err(|e| anyhow!("Create whisper state failed: {e}"))?;
+
+        let range_start = self.config.time_range.map_or(0, |(start_ms, _)| {
+            (start_ms as usize * wav::WHISPER_SAMPLE_RATE as usize / 1000).min(audio_samples.len())
+        });
+
+        // it's detected in the state of the transcription, and whisper takes
+        // the detected language instead of detecting it once more
+        let language_probabilities = if self.is_auto_language() {
+            self.language_probabilities(&mut state, &audio_samples[range_start..])
+        } else {
+            vec![]
+        };
+
+        let mut params = self.full_params(self.initial_prompt().as_deref())?;
+        if let Some((lang, _)) = language_probabilities.first() {
+            params.set_language(Some(lang));
+        }
+
+        if let Some((start_ms, end_ms)) = self.config.time_range {
+            let audio_duration_ms = (audio_data.duration() * 1000.0) as u64;
+            let end_ms = end_ms.min(audio_duration_ms);
+            if start_ms >= end_ms {
+                bail!("time range is out of the audio duration");
+            }
+
+            // whisper.cpp offsets the segment timestamps itself
+            params.set_offset_ms(start_ms.min(i32::MAX as u64) as i32);
+            params.set_duration_ms((end_ms - start_ms).min(i32::MAX as u64) as i32);
+        }
+
+        params.set_progress_callback_safe(progress_cb);
+        params.set_abort_callback_safe(abort_cb);
+
+        // the speakers are labeled before the segments are sent out
+        let mut diarizer = self.diarizer();
+        let mut segments = vec![];
+        let mut segment_reader = self.segment_reader(|mut segment| {
+            if let Some(diarizer) = diarizer.as_mut() {
+                diarizer.diarize(
+                    &audio_samples,
+                    wav::WHISPER_SAMPLE_RATE,
+                    0,
+                    slice::from_mut(&mut segment),
+                );
+            }
+
+            segmemnt_cb(segment.clone());
+            segments.push(segment);
+        });
+        segment_reader.set_new_segment_callback(&mut params);
+
+        state
+            .full(params, &audio_samples)
+            .map_err(|e| anyhow!("Whisper transcribe failed: {e}"))?;
+        drop(segment_reader);
+
+        let mut result =
+            self.extract_transcription_result(&state, segments, audio_data.duration(), start_time);
+        result.language_probabilities = language_probabilities;
+
+        debug!(
+            "Transcript finished，real time factor: {:.2}x",
+            result.real_time_factor()
+        );
+
+        Ok(result)
+    }
+
+    // Transcribe the audio window by window to keep the memory usage low.
+    // The windows are cut at the silences, the text of the previous window
+    // is used as the prompt of the next one, and the timestamps are offset
+    // so the output looks like a single run.
+    fn transcribe_file_in_chunks(
+        &self,
+        audio_path: impl AsRef<Path>,
+        offset_ms: u64,
+        progress_cb: impl FnMut(i32) + 'static,
+        mut segmemnt_cb: impl FnMut(TranscriptionSegment) + 'static,
+        abort_cb: impl FnMut() -> bool + 'static,
+    ) -> Result<TranscriptionResult> {
+        let start_time = std::time::Instant::now();
+
+        // the frames are counted in the whisper sample rate,
+        // and the source frames in the sample rate of the file
+        let mut reader = WavChunkReader::open(&audio_path)?;
+        let source_rate = reader.config().sample_rate as usize;
+        let sample_rate = wav::WHISPER_SAMPLE_RATE as usize;
+        let total_frames = reader.frame_count() * sample_rate / source_rate;
+        let to_source_frames = |frames: usize| frames * source_rate / sample_rate;
+
+        let (start_ms, end_ms) = match self.config.time_range {
+            Some((start_ms, end_ms)) => (start_ms.max(offset_ms), end_ms),
+            None => (offset_ms, u64::MAX),
+        };
+
+        let ms_to_frames = |ms: u64| (ms.saturating_mul(sample_rate as u64) / 1000) as usize;
+        let end_frames = ms_to_frames(end_ms).min(total_frames);
+        let mut offset_frames = ms_to_frames(start_ms).min(end_frames);
+        let source_end_frames = to_source_frames(end_frames);
+        reader.seek(to_source_frames(offset_frames))?;
+
+        // the progress is relative to the time range
+        let range_start_frames = ms_to_frames(self.config.time_range.map_or(0, |r| r.0));
+        let range_frames = end_frames.saturating_sub(range_start_frames).max(1);
+
+        let remaining_source_frames =
+            |reader: &WavChunkReader| source_end_frames.saturating_sub(reader.position());
+
+        // transcribe the rest of the audio at once without the chunk length
+        let chunk_frames = match self.config.chunk_length {
+            _ if self.config.mixed_language.is_some() => MIXED_LANGUAGE_CHUNK_LENGTH * sample_rate,
+            Some(chunk_length) => chunk_length as usize * sample_rate,
+            None => end_frames.saturating_sub(offset_frames).max(1),
+        };
+        let search_frames = (chunk_frames / 3).min(CHUNK_CUT_SEARCH_LENGTH * sample_rate);
+        let vad = EnergyVAD::new(sample_rate as u32).with_threshold(CHUNK_SILENCE_THRESHOLD);
+
+        debug!(
+            "Start whisper infer in chunks，audio duration: {:.2}s, chunk length: {:?}s",
+            reader.duration(),
+            self.config.chunk_length
+        );
+
+        let progress_cb = Rc::new(RefCell::new(progress_cb));
+        let abort_cb = Rc::new(RefCell::new(abort_cb));
+
+        let mut resampler = Resampler::new(source_rate as u32, sample_rate as u32);
+        let mut samples: Vec<f32> = vec![];
+        let mut context = ChunkContext {
+            diarizer: self.diarizer(),
+            ..Default::default()
+        };
+
+        loop {
+            let frames = to_source_frames(chunk_frames.saturating_sub(samples.len()))
+                .min(remaining_source_frames(&reader));
+            let audio_data = reader.read_frames(frames)?;
+            let is_last_chunk = remaining_source_frames(&reader) == 0;
+
+            // the resampler keeps the state between the chunks, so there are no glitches at the cuts
+            samples.extend(resampler.process(&audio_data.to_mono().samples));
+            if is_last_chunk {
+                samples.extend(resampler.flush());
+            }
+
+            if samples.is_empty() {
+                break;
+            }
+
+            let cut_frames = if is_last_chunk {
+                samples.len()
+            } else {
+                cut_offset(&vad, &samples, search_frames)
+            };
+
+            let offset_ms = (offset_frames * 1000 / sample_rate) as u64;
+
+            let (cb, done_frames) = (
+                progress_cb.clone(),
+                offset_frames.saturating_sub(range_start_frames),
+            );
+            let chunk_progress_cb = move |v: i32| {
+                let progress = (done_frames + cut_frames * v.max(0) as usize / 100) * 100;
+                (cb.borrow_mut())((progress / range_frames) as i32);
+            };
+
+            let cb = abort_cb.clone();
+            self.transcribe_chunk(
+                &samples[..cut_frames],
+                offset_ms,
+                &mut context,
+                chunk_progress_cb,
+                &mut segmemnt_cb,
+                move || (cb.borrow_mut())(),
+            )?;
+
+            debug!(
+                "Finished chunk {:.2}s -> {:.2}s",
+                offset_ms as f64 / 1000.0,
+                (offset_frames + cut_frames) as f64 / sample_rate as f64
+            );
+
+            offset_frames += cut_frames;
+            samples.drain(..cut_frames);
+
+            if is_last_chunk && samples.is_empty() {
+                break;
+            }
+        }
+
+        let result = context.into_result(reader.duration(), start_time);
+
+        debug!(
+            "Transcript finished，real time factor: {:.2}x",
+            result.real_time_factor()
+        );
+
+        Ok(result)
+    }
+
+    // The audio data is cut into windows at the silences like the chunks of
+    // a file, so the language of each window is detected on its own.
+    fn transcribe_mixed_language_samples(
+        &self,
+        samples: &[f32],
+        progress_cb: impl FnMut(i32) + 'static,
+        mut segmemnt_cb: impl FnMut(TranscriptionSegment) + 'static,
+        abort_cb: impl FnMut() -> bool + 'static,
+    ) -> Result<TranscriptionResult> {
+        let start_time = std::time::Instant::now();
+
+        let sample_rate = wav::WHISPER_SAMPLE_RATE as usize;
+        let ms_to_frames =
+            |ms: u64| ((ms.saturating_mul(sample_rate as u64) / 1000) as usize).min(samples.len());
+
+        let (start_frames, end_frames) = match self.config.time_range {
+            Some((start_ms, end_ms)) => (ms_to_frames(start_ms), ms_to_frames(end_ms)),
+            None => (0, samples.len()),
+        };
+
+        if start_frames >= end_frames {
+            bail!("time range is out of the audio duration");
+        }
+
+        let window_frames = MIXED_LANGUAGE_CHUNK_LENGTH * sample_rate;
+        let search_frames = (window_frames / 3).min(CHUNK_CUT_SEARCH_LENGTH * sample_rate);
+        let vad = EnergyVAD::new(sample_rate as u32).with_threshold(CHUNK_SILENCE_THRESHOLD);
+
+        let progress_cb = Rc::new(RefCell::new(progress_cb));
+        let abort_cb = Rc::new(RefCell::new(abort_cb));
+
+        let mut context = ChunkContext {
+            diarizer: self.diarizer(),
+            ..Default::default()
+        };
+        let mut offset_frames = start_frames;
+
+        while offset_frames < end_frames {
+            let window = &samples[offset_frames..(offset_frames + window_frames).min(end_frames)];
+            let cut_frames = if offset_frames + window.len() == end_frames {
+                window.len()
+            } else {
+                cut_offset(&vad, window, search_frames)
+            };
+
+            let (cb, done_frames) = (progress_cb.clone(), offset_frames - start_frames);
+            let range_frames = end_frames - start_frames;
+            let window_progress_cb = move |v: i32| {
+                let progress = (done_frames + cut_frames * v.max(0) as usize / 100) * 100;
+                (cb.borrow_mut())((progress / range_frames) as i32);
+            };
+
+            let cb = abort_cb.clone();
+            self.transcribe_chunk(
+                &window[..cut_frames],
+                (offset_frames * 1000 / sample_rate) as u64,
+                &mut context,
+                window_progress_cb,
+                &mut segmemnt_cb,
+                move || (cb.borrow_mut())(),
+            )?;
+
+            offset_frames += cut_frames;
+        }
+
+        let audio_duration = samples.len() as f64 / sample_rate as f64;
+        Ok(context.into_result(audio_duration, start_time))
+    }
+
+    // Transcribe a chunk which starts from `offset_ms` of the audio. In the mixed
+    // language mode, the language of the chunk is detected first. The text of the
+    // previous chunks is used as the prompt if it's in the same language.
+    fn transcribe_chunk(
+        &self,
+        samples: &[f32],
+        offset_ms: u64,
+        context: &mut ChunkContext,
+        progress_cb: impl FnMut(i32) + 'static,
+        segmemnt_cb: &mut dyn FnMut(TranscriptionSegment),
+        abort_cb: impl FnMut() -> bool + 'static,
+    ) -> Result<()> {
         let mut state = self
             .context
             .create_state()
             .map_err(|e| anyhow!("Create whisper state failed: {e}"))?;
 
-        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        // The language of the first chunk is reported. It's detected in the state of
+        // the transcription, and whisper takes the detected language instead of
+        // detecting it once more.
+        let is_first_chunk = context.language.is_none() && self.is_auto_language();
+        let chunk_language = match self.config.mixed_language.as_ref() {
+            Some(candidates) => {
+                let languages = self
+                    .detect_all_languages(&mut state, samples)
+                    .map_err(|e| warn!("{e}"))
+                    .unwrap_or_default();
+
+                if is_first_chunk {
+                    context.language_probabilities =
+                        languages[..languages.len().min(LANGUAGE_DETECT_TOP_N)].to_vec();
+                }
+
+                candidate_language(languages, candidates)
+            }
+            None if is_first_chunk => {
+                context.language_probabilities = self.language_probabilities(&mut state, samples);
+                context
+                    .language_probabilities
+                    .first()
+                    .map(|(lang, _)| lang.clone())
+            }
+            None => None,
+        };
+
+        // the text in another language is a misleading prompt
+        let previous_segments = match context.segments.last() {
+            Some(segment) if chunk_language.is_some() && segment.language != chunk_language => &[],
+            _ => &context.segments[..],
+        };
+
+        let prompt = chunk_prompt(self.initial_prompt().as_deref(), previous_segments);
+        let mut params = self.full_params(prompt.as_deref())?;
+        if chunk_language.is_some() {
+            params.set_language(chunk_language.as_deref());
+        }
+
+        params.set_progress_callback_safe(progress_cb);
+        params.set_abort_callback_safe(abort_cb);
+
+        let chunk_offset = ChunkOffset {
+            segments: context.n_segments,
+            ms: offset_ms,
+        };
+
+        let diarizer = &mut context.diarizer;
+        let mut chunk_segments = vec![];
+        let mut segment_reader = self.segment_reader(|mut segment| {
+            chunk_offset.apply(&mut segment);
+
+            if let Some(diarizer) = diarizer.as_mut() {
+                diarizer.diarize(
+                    samples,
+                    wav::WHISPER_SAMPLE_RATE,
+                    offset_ms,
+                    slice::from_mut(&mut segment),
+                );
+            }
+
+            segmemnt_cb(segment.clone());
+            chunk_segments.push(segment);
+        });
+        segment_reader.set_new_segment_callback(&mut params);
+
+        state
+            .full(params, samples)
+            .map_err(|e| anyhow!("Whisper transcribe failed: {e}"))?;
+        drop(segment_reader);
+
+        if context.language.is_none() {
+            context.language = self.result_language(&state);
+        }
+
+        context.n_segments += state.full_n_segments();
+        context.segments.extend(chunk_segments);
+
+        Ok(())
+    }
+
+    // the glossary is followed by the initial prompt
+    fn initial_prompt(&self) -> Option<String> {
+        let glossary = Glossary::new(&self.config.glossary).prompt();
+
+        match (glossary, self.config.initial_prompt.as_deref()) {
+            (Some(glossary), Some(prompt)) => Some(format!("{glossary} {prompt}")),
+            (Some(glossary), None) => Some(glossary),
+            (None, prompt) => prompt.map(|prompt| prompt.to_string()),
+        }
+    }
+
+    // the segments are read with the glossary applied
+    fn segment_reader<'a>(
+        &self,
+        callback: impl FnMut(TranscriptionSegment) + 'a,
+    ) -> SegmentReader<'a> {
+        SegmentReader {
+            token_eot: self.context.token_eot(),
+            dtw: self.config.dtw.is_some(),
+            glossary: Glossary::new(&self.config.glossary),
+            detect_language: self.is_auto_language() || self.config.mixed_language.is_some(),
+            language: self.config.language.clone(),
+            callback: Box::new(callback),
+        }
+    }
+
+    fn full_params(&self, initial_prompt: Option<&str>) -> Result<FullParams<'_, '_>> {
+        let mut params = FullParams::new((&self.config.sampling_strategy).into());
         params.set_n_threads(self.config.n_threads);
         params.set_translate(self.config.translate);
         params.set_debug_mode(self.config.debug_mode);
         params.set_temperature(self.config.temperature);
         params.set_language(self.config.language.as_ref().map(|x| x.as_str()));
         params.set_token_timestamps(true);
-
-        params.set_progress_callback_safe(progress_cb);
-        params.set_segment_callback_safe(segmemnt_cb);
-        params.set_abort_callback_safe(abort_cb);
+        params.set_tdrz_enable(self.config.diarization == Some(DiarizationMethod::Tinydiarize));
+        params.set_entropy_thold(self.config.entropy_threshold);
+        params.set_logprob_thold(self.config.logprob_threshold);
+        params.set_no_speech_thold(self.config.no_speech_threshold);
+        params.set_suppress_blank(self.config.suppress_blank);
+
+        // it depends on the token timestamps
+        if let Some(length) = self.config.max_segment_length {
+            params.set_max_len(length as i32);
+            params.set_split_on_word(self.config.split_on_word);
+        }
 
         if let Some(path) = &self.config.vad_model_path {
             if !path.exists() {
@@ -248,23 +1061,106 @@ impl WhisperTranscriber {
             params.enable_vad(true);
         }
 
-        if let Some(prompt) = &self.config.initial_prompt {
-            params.set_initial_prompt(prompt.as_str());
+        if let Some(prompt) = initial_prompt {
+            params.set_initial_prompt(prompt);
+        }
+
+        if let Some(hotwords) = &self.hotwords {
+            hotwords.set_logits_filter(&mut params);
         }
 
+        Ok(params)
+    }
+
+    // The top languages of the beginning of the audio
+    pub fn detect_language(&self, audio_data: &AudioData) -> Result<Vec<(String, f32)>> {
+        let samples = if !audio_data.is_whisper_compatible() {
+            self.prepare_audio_samples(audio_data)?
+        } else {
+            audio_data.samples.clone()
+        };
+
+        let mut state = self
+            .context
+            .create_state()
+            .map_err(|e| anyhow!("Create whisper state failed: {e}"))?;
+
+        let mut languages = self.detect_all_languages(&mut state, &samples)?;
+        languages.truncate(LANGUAGE_DETECT_TOP_N);
+
+        Ok(languages)
+    }
+
+    // (language, probability) of all languages in descending order. The mel
+    // spectrogram of the state is overwritten by the transcription later.
+    fn detect_all_languages(
+        &self,
+        state: &mut WhisperState,
+        samples: &[f32],
+    ) -> Result<Vec<(String, f32)>> {
+        let max_len = LANGUAGE_DETECT_LENGTH * wav::WHISPER_SAMPLE_RATE as usize;
+        let samples = &samples[..samples.len().min(max_len)];
+        let n_threads = self.config.n_threads.max(1) as usize;
+
         state
-            .full(params, &audio_samples)
-            .map_err(|e| anyhow!("Whisper transcribe failed: {e}"))?;
+            .pcm_to_mel(samples, n_threads)
+            .map_err(|e| anyhow!("Compute mel spectrogram failed: {e}"))?;
 
-        let result =
-            self.extract_transcription_result(&state, audio_data.duration(), start_time)?;
+        let (_, probabilities) = state
+            .lang_detect(0, n_threads)
+            .map_err(|e| anyhow!("Detect language failed: {e}"))?;
 
-        debug!(
-            "Transcript finished，real time factor: {:.2}x",
-            result.real_time_factor()
-        );
+        let mut languages = probabilities
+            .into_iter()
+            .enumerate()
+            .filter_map(|(id, probability)| {
+                whisper_rs::get_lang_str(id as i32).map(|lang| (lang.to_string(), probability))
+            })
+            .collect::<Vec<_>>();
 
-        Ok(result)
+        languages.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        Ok(languages)
+    }
+
+    fn is_auto_language(&self) -> bool {
+        self.config
+            .language
+            .as_deref()
+            .is_none_or(|lang| lang == "auto")
+    }
+
+    // it's only a report, so the transcription goes on without it
+    fn language_probabilities(
+        &self,
+        state: &mut WhisperState,
+        samples: &[f32],
+    ) -> Vec<(String, f32)> {
+        match self.detect_all_languages(state, samples) {
+            Ok(mut languages) => {
+                languages.truncate(LANGUAGE_DETECT_TOP_N);
+                languages
+            }
+            Err(e) => {
+                warn!("{e}");
+                vec![]
+            }
+        }
+    }
+
+    // the language which whisper detected or the chunk is transcribed in
+    fn result_language(&self, state: &WhisperState) -> Option<String> {
+        if self.is_auto_language() || self.config.mixed_language.is_some() {
+            whisper_rs::get_lang_str(state.full_lang_id_from_state()).map(|lang| lang.to_string())
+        } else {
+            self.config.language.clone()
+        }
+    }
+
+    fn diarizer(&self) -> Option<Diarizer> {
+        self.config
+            .diarization
+            .map(|method| Diarizer::new(method).with_max_speakers(self.config.max_speakers))
     }
 
     fn prepare_audio_samples(&self, audio_data: &AudioData) -> Result<Vec<f32>> {
@@ -276,11 +1172,15 @@ impl WhisperTranscriber {
             debug!("Finished converting to mono channel");
         }
 
-        if audio_data.config.sample_rate != 16000 {
-            bail!(
-                "Not compatible with whisper. Actual sample rate {}, expect 16kHz",
-                audio_data.config.sample_rate
+        if audio_data.config.sample_rate != wav::WHISPER_SAMPLE_RATE {
+            let mono_data = AudioData::new(
+                samples,
+                AudioConfig {
+                    channels: 1,
+                    ..audio_data.config.clone()
+                },
             );
+            samples = mono_data.resample(wav::WHISPER_SAMPLE_RATE).samples;
         }
 
         Ok(samples)
@@ -289,85 +1189,341 @@ impl WhisperTranscriber {
     fn extract_transcription_result(
         &self,
         state: &WhisperState,
+        segments: Vec<TranscriptionSegment>,
         audio_duration: f64,
         start_time: std::time::Instant,
-    ) -> Result<TranscriptionResult> {
-        let audio_duration_ms = (audio_duration * 1000.0) as u64;
-
-        let num_segments = state.full_n_segments();
+    ) -> TranscriptionResult {
+        let full_text = segments
+            .iter()
+            .map(|s| s.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
 
-        let mut segments = Vec::new();
-        let mut full_text = String::new();
+        TranscriptionResult {
+            text: full_text,
+            language: self.result_language(state),
+            language_probabilities: vec![],
+            segments,
+            processing_time: start_time.elapsed().as_millis() as u64,
+            audio_duration: (audio_duration * 1000.0) as u64,
+        }
+    }
+}
 
-        for i in 0..num_segments {
-            let Some(segment) = state.get_segment(i) else {
-                continue;
-            };
+// The transcription state which is carried from chunk to chunk
+#[derive(Default)]
+struct ChunkContext {
+    segments: Vec<TranscriptionSegment>,
+    n_segments: i32, // the whisper segments, including the empty ones
+    language: Option<String>,
+    language_probabilities: Vec<(String, f32)>,
+    diarizer: Option<Diarizer>,
+}
 
-            let segment_text = segment.to_str().unwrap_or("").trim().to_string();
+impl ChunkContext {
+    fn into_result(
+        self,
+        audio_duration: f64,
+        start_time: std::time::Instant,
+    ) -> TranscriptionResult {
+        TranscriptionResult {
+            text: self
+                .segments
+                .iter()
+                .map(|s| s.text.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+            language: self.language,
+            language_probabilities: self.language_probabilities,
+            segments: self.segments,
+            processing_time: start_time.elapsed().as_millis() as u64,
+            audio_duration: (audio_duration * 1000.0) as u64,
+        }
+    }
+}
 
-            if segment_text.is_empty() {
-                continue;
-            }
+// The most possible candidate language of the chunk.
+// None is to let whisper detect it in the transcription.
+fn candidate_language(languages: Vec<(String, f32)>, candidates: &[String]) -> Option<String> {
+    languages
+        .into_iter()
+        .map(|(lang, _)| lang)
+        .find(|lang| candidates.is_empty() || candidates.contains(lang))
+}
 
-            let start_time = (segment.start_timestamp() as u64) * 10;
-            let end_time = (segment.end_timestamp() as u64) * 10;
-            let confidence = self.calculate_segment_confidence(state, i)?;
+// Cut at the silence in the tail of the samples, or at the end if there's none
+fn cut_offset(vad: &EnergyVAD, samples: &[f32], search_frames: usize) -> usize {
+    let search_start = samples.len().saturating_sub(search_frames);
+    vad.detect_cut_offset(&samples[search_start..])
+        .map_or(samples.len(), |offset| search_start + offset)
+}
 
-            segments.push(TranscriptionSegment {
-                index: i as i32 + 1,
-                start_time,
-                end_time,
-                text: segment_text.clone(),
-                confidence,
-            });
+// The segments of a chunk are relative to the chunk, so they are shifted
+// by the segments and the duration of the previous chunks.
+#[derive(Debug, Clone, Copy)]
+struct ChunkOffset {
+    segments: i32, // the segments of the previous chunks
+    ms: u64,
+}
 
-            if !full_text.is_empty() {
-                full_text.push(' ');
-            }
-            full_text.push_str(&segment_text);
+impl ChunkOffset {
+    fn apply(&self, segment: &mut TranscriptionSegment) {
+        segment.index += self.segments;
+        segment.start_time += self.ms;
+        segment.end_time += self.ms;
+        for word in segment.words.iter_mut() {
+            word.start_time += self.ms;
+            word.end_time += self.ms;
         }
+    }
+}
 
-        let processing_time = start_time.elapsed().as_millis() as u64;
-        Ok(TranscriptionResult {
-            text: full_text,
-            language: self.config.language.clone(),
-            segments,
-            processing_time,
-            audio_duration: audio_duration_ms,
-        })
+// Read the new segments in the callback of whisper.cpp, so they are sent out
+// with the same details as the result, e.g. the confidence and the words.
+struct SegmentReader<'a> {
+    token_eot: WhisperTokenId,
+    dtw: bool,
+    glossary: Glossary,
+    detect_language: bool,
+    language: Option<String>,
+    callback: Box<dyn FnMut(TranscriptionSegment) + 'a>,
+}
+
+impl SegmentReader<'_> {
+    // The reader must outlive the params
+    fn set_new_segment_callback(&mut self, params: &mut FullParams) {
+        unsafe {
+            params.set_new_segment_callback(Some(new_segment_callback));
+            params.set_new_segment_callback_user_data(self as *mut Self as *mut c_void);
+        }
     }
 
-    fn calculate_segment_confidence(
+    // The empty segments are skipped, and the timestamps are relative to the audio data
+    unsafe fn read(
         &self,
-        state: &WhisperState,
-        segment_index: i32,
-    ) -> Result<f32> {
-        let Some(segment) = state.get_segment(segment_index) else {
-            return Ok(0.0);
+        ctx: *mut WhisperSysContext,
+        state: *mut WhisperSysState,
+        index: c_int,
+    ) -> Option<TranscriptionSegment> {
+        let text =
+            unsafe { whisper_rs_sys::whisper_full_get_segment_text_from_state(state, index) };
+        if text.is_null() {
+            return None;
+        }
+
+        let text = self
+            .glossary
+            .apply(unsafe { CStr::from_ptr(text) }.to_string_lossy().trim());
+        if text.is_empty() {
+            return None;
+        }
+
+        let (start_time, end_time) = unsafe {
+            (
+                whisper_rs_sys::whisper_full_get_segment_t0_from_state(state, index),
+                whisper_rs_sys::whisper_full_get_segment_t1_from_state(state, index),
+            )
         };
-        let token_count = segment.n_tokens();
+        let (start_time, end_time) = (start_time.max(0) as u64 * 10, end_time.max(0) as u64 * 10);
+
+        let n_tokens = unsafe { whisper_rs_sys::whisper_full_n_tokens_from_state(state, index) };
+        let tokens = (0..n_tokens)
+            .map(|token_index| unsafe {
+                let data = whisper_rs_sys::whisper_full_get_token_data_from_state(
+                    state,
+                    index,
+                    token_index,
+                );
+                let text = whisper_rs_sys::whisper_full_get_token_text_from_state(
+                    ctx,
+                    state,
+                    index,
+                    token_index,
+                );
+
+                let bytes = if text.is_null() {
+                    vec![]
+                } else {
+                    CStr::from_ptr(text).to_bytes().to_vec()
+                };
+
+                (data, bytes)
+            })
+            .collect::<Vec<_>>();
+
+        // the average probability of the tokens
+        let confidence = match tokens.len() {
+            0 => 0.0,
+            n => tokens.iter().map(|(data, _)| data.p).sum::<f32>() / n as f32,
+        };
+
+        let timings = tokens
+            .into_iter()
+            // skip special tokens, e.g. [_BEG_], [_TT_xxx]
+            .filter(|(data, _)| data.id < self.token_eot)
+            .map(|(data, bytes)| TokenTiming {
+                bytes,
+                // the DTW timestamps are more accurate
+                start_time: match data.t_dtw {
+                    t_dtw if self.dtw && t_dtw >= 0 => t_dtw as u64 * 10,
+                    _ => data.t0.max(0) as u64 * 10,
+                },
+                end_time: data.t1.max(0) as u64 * 10,
+                probability: data.p,
+            })
+            .collect::<Vec<_>>();
+
+        let language = if self.detect_language {
+            let lang_id = unsafe { whisper_rs_sys::whisper_full_lang_id_from_state(state) };
+            whisper_rs::get_lang_str(lang_id).map(|lang| lang.to_string())
+        } else {
+            self.language.clone()
+        };
+
+        Some(TranscriptionSegment {
+            index: index + 1,
+            start_time,
+            end_time,
+            text,
+            confidence,
+            words: tokens_to_words(&timings, start_time, end_time),
+            speaker: None,
+            speaker_turn: unsafe {
+                whisper_rs_sys::whisper_full_get_segment_speaker_turn_next_from_state(state, index)
+            },
+            language,
+            hallucination: vec![],
+        })
+    }
+}
+
+unsafe extern "C" fn new_segment_callback(
+    ctx: *mut WhisperSysContext,
+    state: *mut WhisperSysState,
+    n_new: c_int,
+    user_data: *mut c_void,
+) {
+    if user_data.is_null() || state.is_null() {
+        return;
+    }
+
+    let reader = unsafe { &mut *(user_data as *mut SegmentReader) };
+    let n_segments = unsafe { whisper_rs_sys::whisper_full_n_segments_from_state(state) };
 
-        if token_count == 0 {
-            return Ok(0.0);
+    for index in (n_segments - n_new).max(0)..n_segments {
+        if let Some(segment) = unsafe { reader.read(ctx, state, index) } {
+            (reader.callback)(segment);
         }
+    }
+}
 
-        let mut total_prob = 0.0;
-        let mut valid_tokens = 0;
+fn chunk_prompt(initial_prompt: Option<&str>, segments: &[TranscriptionSegment]) -> Option<String> {
+    let context = segments[segments.len().saturating_sub(CHUNK_PROMPT_SEGMENTS)..]
+        .iter()
+        .map(|s| s.text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    match initial_prompt {
+        Some(prompt) if context.is_empty() => Some(prompt.to_string()),
+        Some(prompt) => Some(format!("{prompt} {context}")),
+        None if context.is_empty() => None,
+        None => Some(context),
+    }
+}
 
-        for token_index in 0..token_count {
-            if let Some(token) = segment.get_token(token_index) {
-                total_prob += token.token_probability();
-                valid_tokens += 1;
+// A token may only contain a part of a multi-byte character. A new word
+// starts from a token with a leading whitespace, and each CJK character is a word.
+fn tokens_to_words(
+    tokens: &[TokenTiming],
+    segment_start_time: u64,
+    segment_end_time: u64,
+) -> Vec<TranscriptionWord> {
+    let tokens = tokens.iter().map(|token| (token.clone(), 1)).collect();
+
+    // join the bytes of a multi-byte character first
+    let chars = merge_tokens(tokens, |word, token| {
+        std::str::from_utf8(&word.bytes).is_err()
+            && token
+                .bytes
+                .first()
+                .is_some_and(|byte| (0x80..0xC0).contains(byte))
+    });
+
+    let words = merge_tokens(chars, |word, token| {
+        let last_char = String::from_utf8_lossy(&word.bytes).chars().last();
+        let first_char = String::from_utf8_lossy(&token.bytes).chars().next();
+
+        let is_new_word = first_char.is_some_and(|c| c.is_whitespace() || is_cjk_char(c))
+            || (last_char.is_some_and(|c| is_cjk_char(c) || is_cjk_punctuation(c))
+                && first_char.is_some_and(|c| c.is_alphanumeric()));
+
+        !is_new_word
+    });
+
+    words
+        .into_iter()
+        .filter_map(|(word, count)| {
+            let text = String::from_utf8_lossy(&word.bytes).trim().to_string();
+            if text.is_empty() {
+                return None;
             }
-        }
 
-        if valid_tokens > 0 {
-            Ok(total_prob / valid_tokens as f32)
-        } else {
-            Ok(0.5)
+            let start_time = word
+                .start_time
+                .clamp(segment_start_time, segment_end_time.max(segment_start_time));
+            let end_time = word
+                .end_time
+                .clamp(start_time, segment_end_time.max(start_time));
+
+            Some(TranscriptionWord {
+                text,
+                start_time,
+                end_time,
+                probability: word.probability / count as f32,
+            })
+        })
+        .collect()
+}
+
+// The probability is summed up and the token count is kept for averaging
+fn merge_tokens(
+    tokens: Vec<(TokenTiming, usize)>,
+    should_merge: impl Fn(&TokenTiming, &TokenTiming) -> bool,
+) -> Vec<(TokenTiming, usize)> {
+    let mut words: Vec<(TokenTiming, usize)> = vec![];
+
+    for (token, count) in tokens.into_iter() {
+        match words.last_mut() {
+            Some((word, word_count)) if should_merge(word, &token) => {
+                word.bytes.extend_from_slice(&token.bytes);
+                word.end_time = word.end_time.max(token.end_time);
+                word.probability += token.probability;
+                *word_count += count;
+            }
+            _ => words.push((token, count)),
         }
     }
+
+    words
+}
+
+fn is_cjk_punctuation(c: char) -> bool {
+    matches!(c as u32,
+        0x3000..=0x303F     // CJK Symbols and Punctuation
+        | 0xFF00..=0xFFEF   // Halfwidth and Fullwidth Forms
+    )
+}
+
+fn is_cjk_char(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF     // Hiragana and Katakana
+        | 0x3400..=0x4DBF   // CJK Extension A
+        | 0x4E00..=0x9FFF   // CJK Unified Ideographs
+        | 0xAC00..=0xD7AF   // Hangul Syllables
+        | 0xF900..=0xFAFF   // CJK Compatibility Ideographs
+        | 0x20000..=0x2FA1F // CJK Extension B-F and Compatibility Supplement
+    )
 }
 
 pub fn convert_to_compatible_audio(
@@ -377,17 +1533,35 @@ pub fn convert_to_compatible_audio(
     progress_cb: impl FnMut(i32) + 'static,
 ) -> Result<()> {
     is_valid_aduio_file(&output)?;
+
+    #[cfg(feature = "symphonia")]
+    if decoder::is_supported(input.as_ref()) {
+        decoder::convert_to_whisper_compatible_audio(&input, &output, cancel, progress_cb)?;
+        return wav::is_whisper_compatible(&output);
+    }
+
     ffmpeg::convert_to_whisper_compatible_audio(&input, &output, cancel, progress_cb)?;
     wav::is_whisper_compatible(&output)?;
 
     Ok(())
 }
 
+// The files which are converted to whisper compatible audio without ffmpeg
+pub fn is_converted_in_process(input: impl AsRef<Path>) -> bool {
+    #[cfg(feature = "symphonia")]
+    if decoder::is_supported(&input) {
+        return true;
+    }
+
+    let _ = input;
+    false
+}
+
 pub async fn transcribe_file(
     config: WhisperConfig,
     audio_path: impl AsRef<Path>,
     progress_cb: impl FnMut(i32) + 'static,
-    segmemnt_cb: impl FnMut(SegmentCallbackData) + 'static,
+    segmemnt_cb: impl FnMut(TranscriptionSegment) + 'static,
     abort_cb: impl FnMut() -> bool + 'static,
 ) -> Result<TranscriptionResult> {
     let transcriber = WhisperTranscriber::new(config)?;
@@ -396,6 +1570,20 @@ pub async fn transcribe_file(
         .await
 }
 
+pub async fn resume_transcribe_file(
+    config: WhisperConfig,
+    audio_path: impl AsRef<Path>,
+    offset_ms: u64,
+    progress_cb: impl FnMut(i32) + 'static,
+    segmemnt_cb: impl FnMut(TranscriptionSegment) + 'static,
+    abort_cb: impl FnMut() -> bool + 'static,
+) -> Result<TranscriptionResult> {
+    let transcriber = WhisperTranscriber::new(config)?;
+    transcriber
+        .resume_transcribe_file(audio_path, offset_ms, progress_cb, segmemnt_cb, abort_cb)
+        .await
+}
+
 pub fn save_ggml_silero_vad_model(path: impl AsRef<Path>) -> Result<()> {
     let path = path.as_ref();
     fs::write(&path, GGML_SILERO_VAD_MODEL)
@@ -417,3 +1605,192 @@ fn is_valid_aduio_file(audio_path: impl AsRef<Path>) -> Result<()> {
 
     Ok(())
 }
+
+fn is_supported_audio_file(audio_path: impl AsRef<Path>) -> Result<()> {
+    #[cfg(feature = "symphonia")]
+    if decoder::is_supported(&audio_path) {
+        return Ok(());
+    }
+
+    is_valid_aduio_file(audio_path)
+}
+
+// The non-wav audio is decoded into a temporary wav file, so it's
+// transcribed in the same way. The file is removed after transcribing.
+#[cfg(feature = "symphonia")]
+struct DecodedWavFile {
+    path: PathBuf,
+}
+
+#[cfg(feature = "symphonia")]
+impl DecodedWavFile {
+    fn new(audio_path: &Path) -> Result<Option<Self>> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        if !decoder::is_supported(audio_path) {
+            return Ok(None);
+        }
+
+        let path = std::env::temp_dir().join(format!(
+            "transcribe-{}-{}.wav",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        debug!("Decode {} to {}", audio_path.display(), path.display());
+        let file = Self { path };
+        decoder::convert_to_whisper_compatible_audio(
+            audio_path,
+            &file.path,
+            Arc::new(AtomicBool::new(false)),
+            |_| {},
+        )?;
+
+        Ok(Some(file))
+    }
+}
+
+#[cfg(feature = "symphonia")]
+impl Drop for DecodedWavFile {
+    fn drop(&mut self) {
+        _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(text: &[u8], start_time: u64, end_time: u64) -> TokenTiming {
+        TokenTiming {
+            bytes: text.to_vec(),
+            start_time,
+            end_time,
+            probability: 0.5,
+        }
+    }
+
+    #[test]
+    fn test_tokens_to_words() {
+        let tokens = vec![
+            token(b" Hello", 0, 300),
+            token(b",", 300, 350),
+            token(b" wor", 400, 600),
+            token(b"ld", 600, 900),
+            token(b"!", 900, 1200),
+        ];
+
+        let words = tokens_to_words(&tokens, 100, 1000);
+        let texts = words.iter().map(|w| w.text.as_str()).collect::<Vec<_>>();
+
+        assert_eq!(texts, vec!["Hello,", "world!"]);
+        assert_eq!((words[0].start_time, words[0].end_time), (100, 350));
+        assert_eq!((words[1].start_time, words[1].end_time), (400, 1000));
+        assert_eq!(words[1].probability, 0.5);
+    }
+
+    #[test]
+    fn test_tokens_to_words_cjk() {
+        let hao = "好".as_bytes();
+        let tokens = vec![
+            token("你".as_bytes(), 0, 200),
+            token(&hao[..1], 200, 300),
+            token(&hao[1..], 300, 400),
+            token("，".as_bytes(), 400, 450),
+            token("AI".as_bytes(), 500, 800),
+        ];
+
+        let words = tokens_to_words(&tokens, 0, 1000);
+        let texts = words.iter().map(|w| w.text.as_str()).collect::<Vec<_>>();
+
+        assert_eq!(texts, vec!["你", "好，", "AI"]);
+        assert_eq!((words[1].start_time, words[1].end_time), (200, 450));
+    }
+
+    #[test]
+    fn test_chunk_offset() {
+        // the offset of a resumed transcription isn't a multiple of 10ms
+        let offset = ChunkOffset {
+            segments: 3,
+            ms: 1235,
+        };
+
+        let mut segment = TranscriptionSegment {
+            index: 2,
+            start_time: 1500,
+            end_time: 2750,
+            text: "Hello world".to_string(),
+            confidence: 0.9,
+            words: tokens_to_words(&[token(b" Hello", 1500, 2000)], 1500, 2750),
+            speaker: None,
+            language: None,
+            hallucination: vec![],
+            speaker_turn: false,
+        };
+        offset.apply(&mut segment);
+
+        assert_eq!(
+            (segment.index, segment.start_time, segment.end_time),
+            (5, 2735, 3985)
+        );
+        assert_eq!(
+            (segment.words[0].start_time, segment.words[0].end_time),
+            (2735, 3235)
+        );
+    }
+
+    #[test]
+    fn test_context_params() {
+        let config = WhisperConfig::default();
+        let params = config.context_params();
+        assert_eq!(params.use_gpu, GPU_BACKEND_ENABLED);
+        assert!(!params.flash_attn);
+        assert!(matches!(params.dtw_parameters.mode, DtwMode::None));
+
+        // the CPU path
+        let config = WhisperConfig::default()
+            .with_use_gpu(false)
+            .with_gpu_device(1)
+            .with_dtw(DtwPreset::Base);
+        let params = config.context_params();
+        assert!(!params.use_gpu);
+        assert_eq!(params.gpu_device, 1);
+        assert!(matches!(
+            params.dtw_parameters.mode,
+            DtwMode::ModelPreset {
+                model_preset: DtwModelPreset::Base
+            }
+        ));
+
+        let params = WhisperConfig::default()
+            .with_dtw(DtwPreset::TopMost { n_top: 2 })
+            .context_params();
+        assert!(matches!(
+            params.dtw_parameters.mode,
+            DtwMode::TopMost { n_top: 2 }
+        ));
+    }
+
+    #[test]
+    fn test_validate_context_params() {
+        let model_path = std::env::temp_dir().join("whispercap-test-validate-model.bin");
+        fs::write(&model_path, b"").unwrap();
+
+        let config = WhisperConfig::new(&model_path).with_use_gpu(false);
+        assert!(config.validate().is_ok());
+        assert!(config.clone().with_gpu_device(-1).validate().is_err());
+        assert!(config
+            .clone()
+            .with_flash_attn(true)
+            .with_dtw(DtwPreset::Tiny)
+            .validate()
+            .is_err());
+        assert!(config
+            .with_dtw(DtwPreset::TopMost { n_top: 0 })
+            .validate()
+            .is_err());
+
+        _ = fs::remove_file(&model_path);
+    }
+}
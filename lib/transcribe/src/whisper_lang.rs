@@ -523,6 +523,14 @@ impl WhisperLang {
         lang.map(|l| l.to_string())
     }
 
+    // e.g. "zh" -> "Chinese", "en" -> "English"
+    pub fn get_long_name_from_code(code: &str) -> Option<&'static str> {
+        Self::all_languages()
+            .into_iter()
+            .find(|(_, item, _)| *item == code)
+            .map(|(_, _, long_name)| long_name)
+    }
+
     pub fn from_long_name(long_name: &str) -> Option<Self> {
         match long_name.to_lowercase().as_str() {
             "auto" | "auto detect" => Some(WhisperLang::Auto),
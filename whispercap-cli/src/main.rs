@@ -0,0 +1,555 @@
+use anyhow::{bail, Context, Result};
+use clap::{Parser, ValueEnum};
+use log::{debug, info, warn};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{atomic::AtomicBool, Arc},
+};
+use transcribe::{
+    diarization::{DiarizationMethod, DEFAULT_MAX_SPEAKERS},
+    hallucination::{HallucinationAction, HallucinationDetector},
+    hotword::DEFAULT_HOTWORD_BIAS,
+    subtitle::{self, Subtitle},
+    wav,
+    whisper::{
+        self, DtwPreset, SamplingStrategy, TranscriptionResult, WhisperConfig, WhisperTranscriber,
+    },
+};
+
+// the files of a directory are picked by the extensions
+const MEDIA_EXTENSIONS: [&str; 22] = [
+    "wav", "mp3", "flac", "ogg", "oga", "opus", "m4a", "aac", "wma", "aiff", "amr", "mp4", "mkv",
+    "mov", "avi", "webm", "flv", "wmv", "m4v", "ts", "mpg", "mpeg",
+];
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "whispercap-cli",
+    version,
+    about = "A headless tool to transcribe media files into subtitles.",
+    long_about = None
+)]
+struct Args {
+    /// Input media file or directory
+    #[arg(short, long)]
+    input: PathBuf,
+
+    /// Whisper model path
+    #[arg(short, long)]
+    model: PathBuf,
+
+    /// Language code, e.g. `zh`, `en`. Use `auto` to detect it
+    #[arg(short, long, default_value = "auto")]
+    language: String,
+
+    /// Output directory. Default is the directory of the media file
+    #[arg(short, long)]
+    output_dir: Option<PathBuf>,
+
+    /// Output subtitle formats
+    #[arg(short, long, value_delimiter = ',', default_value = "srt")]
+    formats: Vec<Format>,
+
+    /// Silero VAD model path
+    #[arg(long)]
+    vad_model: Option<PathBuf>,
+
+    /// Run whisper on the CPU even if it's built with a GPU backend
+    #[arg(long, default_value_t = false)]
+    no_gpu: bool,
+
+    /// GPU device index
+    #[arg(long, default_value_t = 0)]
+    gpu_device: i32,
+
+    /// Enable flash attention
+    #[arg(long, default_value_t = false)]
+    flash_attn: bool,
+
+    /// Use the DTW token timestamps with the alignment heads of the model
+    #[arg(long)]
+    dtw: Option<Dtw>,
+
+    /// Number of threads used by whisper
+    #[arg(short, long)]
+    threads: Option<i32>,
+
+    /// Translate into English
+    #[arg(long, default_value_t = false)]
+    translate: bool,
+
+    /// Number of candidates of greedy decoding
+    #[arg(long, default_value_t = 1)]
+    best_of: i32,
+
+    /// Use beam search decoding with the beam size instead of greedy decoding
+    #[arg(long)]
+    beam_size: Option<i32>,
+
+    /// Beam search patience
+    #[arg(long, default_value_t = -1.0, allow_negative_numbers = true)]
+    patience: f32,
+
+    /// Initial prompt
+    #[arg(short, long)]
+    prompt: Option<String>,
+
+    /// Domain terms separated by commas, e.g. `Kubernetes,Slint`. They are in
+    /// the prompt, and the similar words in the subtitles are replaced with them
+    #[arg(long, value_delimiter = ',')]
+    glossary: Vec<String>,
+
+    /// Words boosted in decoding, separated by commas. The bias is optional,
+    /// e.g. `Kubernetes:3,Slint`
+    #[arg(long, value_delimiter = ',', value_parser = parse_hotword)]
+    hotwords: Vec<(String, f32)>,
+
+    /// Transcribe long recordings in chunks of the given seconds (at least 30) to save memory
+    #[arg(long)]
+    chunk_length: Option<u32>,
+
+    /// Detect the language of every 10 seconds chunk for the recordings switching
+    /// between languages. The candidate languages are optional, e.g. `zh,en`
+    #[arg(long, value_delimiter = ',', num_args = 0..)]
+    mixed_language: Option<Vec<String>>,
+
+    /// Label the subtitles with speakers
+    #[arg(long)]
+    diarize: Option<Diarize>,
+
+    /// Maximum number of speakers of the diarization
+    #[arg(long, default_value_t = DEFAULT_MAX_SPEAKERS)]
+    max_speakers: usize,
+
+    /// Prefix the speakers to the text of the srt, txt and sbv files, e.g. `Speaker 1: hello`
+    #[arg(long, default_value_t = false)]
+    speaker_prefix: bool,
+
+    /// Remove the repeated lines and the text over the silence which whisper made up
+    #[arg(long, default_value_t = false)]
+    remove_hallucinations: bool,
+
+    /// Convert traditional Chinese to simplified Chinese
+    #[arg(long, default_value_t = false)]
+    simplified_chinese: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Format {
+    Srt,
+    Vtt,
+    Ass,
+    Txt,
+    Json,
+    Csv,
+    Ttml,
+    Sbv,
+    Lrc,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Diarize {
+    /// Whisper's tinydiarize, it needs a `tdrz` model
+    Tinydiarize,
+    /// Cluster the voices of the segments
+    Clustering,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Dtw {
+    TinyEn,
+    Tiny,
+    BaseEn,
+    Base,
+    SmallEn,
+    Small,
+    MediumEn,
+    Medium,
+    LargeV1,
+    LargeV2,
+    LargeV3,
+    LargeV3Turbo,
+}
+
+impl From<Dtw> for DtwPreset {
+    fn from(dtw: Dtw) -> Self {
+        match dtw {
+            Dtw::TinyEn => DtwPreset::TinyEn,
+            Dtw::Tiny => DtwPreset::Tiny,
+            Dtw::BaseEn => DtwPreset::BaseEn,
+            Dtw::Base => DtwPreset::Base,
+            Dtw::SmallEn => DtwPreset::SmallEn,
+            Dtw::Small => DtwPreset::Small,
+            Dtw::MediumEn => DtwPreset::MediumEn,
+            Dtw::Medium => DtwPreset::Medium,
+            Dtw::LargeV1 => DtwPreset::LargeV1,
+            Dtw::LargeV2 => DtwPreset::LargeV2,
+            Dtw::LargeV3 => DtwPreset::LargeV3,
+            Dtw::LargeV3Turbo => DtwPreset::LargeV3Turbo,
+        }
+    }
+}
+
+impl From<Diarize> for DiarizationMethod {
+    fn from(diarize: Diarize) -> Self {
+        match diarize {
+            Diarize::Tinydiarize => DiarizationMethod::Tinydiarize,
+            Diarize::Clustering => DiarizationMethod::Clustering,
+        }
+    }
+}
+
+impl Format {
+    fn extension(&self) -> &'static str {
+        match self {
+            Format::Srt => "srt",
+            Format::Vtt => "vtt",
+            Format::Ass => "ass",
+            Format::Txt => "txt",
+            Format::Json => "json",
+            Format::Csv => "csv",
+            Format::Ttml => "ttml",
+            Format::Sbv => "sbv",
+            Format::Lrc => "lrc",
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .parse_default_env()
+        .init();
+
+    let args = Args::parse();
+
+    let media_files = collect_media_files(&args.input)?;
+    if media_files.is_empty() {
+        bail!("No media file found in {}", args.input.display());
+    }
+
+    // ffmpeg is only required by the files which can't be decoded in process
+    let ffmpeg_file = media_files.iter().find(|path| !is_decoded_in_process(path));
+    if let Some(media_file) = ffmpeg_file
+        && !ffmpeg::is_installed()
+    {
+        bail!(
+            "Can't find ffmpeg, please install it first. It's required to convert {}",
+            media_file.display()
+        );
+    }
+
+    if let Some(dir) = &args.output_dir {
+        fs::create_dir_all(dir).with_context(|| format!("Create {} failed", dir.display()))?;
+    }
+
+    let transcriber = WhisperTranscriber::new(whisper_config(&args))?;
+
+    let total = media_files.len();
+    let mut failed = 0;
+    for (index, media_file) in media_files.iter().enumerate() {
+        info!("[{}/{total}] {}", index + 1, media_file.display());
+
+        if let Err(e) = transcribe_media_file(&transcriber, &args, media_file).await {
+            warn!("Transcribe {} failed: {e:?}", media_file.display());
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        bail!("{failed}/{total} files failed");
+    }
+
+    info!("All {total} files finished");
+
+    Ok(())
+}
+
+fn whisper_config(args: &Args) -> WhisperConfig {
+    let mut config = WhisperConfig::new(&args.model)
+        .with_language(&args.language)
+        .with_translate(args.translate)
+        .with_use_gpu(!args.no_gpu && whisper::GPU_BACKEND_ENABLED)
+        .with_gpu_device(args.gpu_device)
+        .with_flash_attn(args.flash_attn)
+        .with_sampling_strategy(match args.beam_size {
+            Some(beam_size) => SamplingStrategy::BeamSearch {
+                beam_size,
+                patience: args.patience,
+            },
+            None => SamplingStrategy::Greedy {
+                best_of: args.best_of,
+            },
+        });
+
+    if let Some(dtw) = args.dtw {
+        config = config.with_dtw(dtw.into());
+    }
+
+    if let Some(path) = &args.vad_model {
+        config = config.with_vad_model_path(path);
+    }
+
+    if let Some(threads) = args.threads {
+        config = config.with_threads(threads);
+    }
+
+    if let Some(prompt) = &args.prompt {
+        config = config.with_initial_prompt(prompt);
+    }
+
+    if !args.glossary.is_empty() {
+        config = config.with_glossary(&args.glossary);
+    }
+
+    if !args.hotwords.is_empty() {
+        config = config.with_hotwords(args.hotwords.clone());
+    }
+
+    if let Some(chunk_length) = args.chunk_length {
+        config = config.with_chunk_length(chunk_length);
+    }
+
+    if let Some(languages) = &args.mixed_language {
+        config = config.with_mixed_language(languages);
+    }
+
+    if let Some(diarize) = args.diarize {
+        config = config.with_diarization(diarize.into(), args.max_speakers);
+    }
+
+    config
+}
+
+fn parse_hotword(hotword: &str) -> Result<(String, f32)> {
+    match hotword.rsplit_once(':') {
+        Some((word, bias)) => Ok((
+            word.trim().to_string(),
+            bias.trim()
+                .parse()
+                .with_context(|| format!("Invalid bias of hotword: {hotword}"))?,
+        )),
+        None => Ok((hotword.trim().to_string(), DEFAULT_HOTWORD_BIAS)),
+    }
+}
+
+fn remove_hallucinations(audio_path: &Path, result: &mut TranscriptionResult) -> Result<()> {
+    let audio_data = wav::read_file(audio_path)?.to_mono();
+    let count = HallucinationDetector::new().apply(
+        &mut result.segments,
+        Some((&audio_data.samples, audio_data.config.sample_rate)),
+        HallucinationAction::Remove,
+    );
+
+    if count > 0 {
+        info!("Removed {count} hallucinated subtitles");
+    }
+
+    Ok(())
+}
+
+fn collect_media_files(input: &Path) -> Result<Vec<PathBuf>> {
+    if input.is_file() {
+        return Ok(vec![input.to_path_buf()]);
+    }
+
+    if !input.is_dir() {
+        bail!("Can't find file: {}", input.display());
+    }
+
+    let mut files = fs::read_dir(input)
+        .with_context(|| format!("Read {} failed", input.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            let is_media = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| {
+                    MEDIA_EXTENSIONS
+                        .iter()
+                        .any(|item| item.eq_ignore_ascii_case(ext))
+                });
+
+            if !is_media {
+                debug!("Skip non-media file: {}", path.display());
+            }
+            is_media
+        })
+        .collect::<Vec<_>>();
+
+    files.sort();
+
+    // the outputs and the temporary wav file are named after the stem,
+    // so `talk.mp3` and `talk.mp4` would overwrite each other
+    let mut stems = HashMap::new();
+    for path in &files {
+        if let Some(stem) = path.file_stem()
+            && let Some(other) = stems.insert(stem, path)
+        {
+            bail!(
+                "{} and {} have the same file name, please rename one of them",
+                other.display(),
+                path.display()
+            );
+        }
+    }
+
+    Ok(files)
+}
+
+// the wav files are transcribed directly in any sample rate
+fn is_wav_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("wav"))
+        && wav::WavChunkReader::open(path).is_ok()
+}
+
+fn is_decoded_in_process(path: &Path) -> bool {
+    #[cfg(feature = "symphonia")]
+    if transcribe::decoder::is_supported(path) {
+        return true;
+    }
+
+    is_wav_file(path)
+}
+
+async fn transcribe_media_file(
+    transcriber: &WhisperTranscriber,
+    args: &Args,
+    media_file: &Path,
+) -> Result<()> {
+    let stem = media_file
+        .file_stem()
+        .with_context(|| format!("Invalid file name: {}", media_file.display()))?
+        .to_string_lossy()
+        .to_string();
+
+    let output_dir = match &args.output_dir {
+        Some(dir) => dir.clone(),
+        None => media_file
+            .parent()
+            .map(|dir| dir.to_path_buf())
+            .unwrap_or_default(),
+    };
+
+    let is_wav = is_wav_file(media_file);
+    let audio_path = if is_wav {
+        media_file.to_path_buf()
+    } else {
+        let audio_path =
+            std::env::temp_dir().join(format!("whispercap-cli-{}-{stem}.wav", std::process::id()));
+
+        info!("Convert to whisper compatible audio file...");
+        whisper::convert_to_compatible_audio(
+            media_file,
+            &audio_path,
+            Arc::new(AtomicBool::new(false)),
+            |v| debug!("convert to audio progress: {v}%"),
+        )?;
+
+        audio_path
+    };
+
+    let result = transcriber
+        .transcribe_file(
+            &audio_path,
+            |v: i32| info!("whisper transcribe progress: {v}%"),
+            |_| {},
+            || false,
+        )
+        .await
+        .and_then(|mut result| {
+            if args.remove_hallucinations {
+                remove_hallucinations(&audio_path, &mut result)?;
+            }
+            Ok(result)
+        });
+
+    if !is_wav {
+        _ = fs::remove_file(&audio_path);
+    }
+
+    let result = result?;
+    if !result.language_probabilities.is_empty() {
+        let languages = result
+            .language_probabilities
+            .iter()
+            .map(|(lang, probability)| format!("{lang} {:.1}%", probability * 100.0))
+            .collect::<Vec<_>>();
+        info!("Detected language: {}", languages.join(", "));
+    }
+
+    let mut subtitles = subtitle::transcription_to_subtitle(&result);
+    if args.simplified_chinese {
+        for item in subtitles.iter_mut() {
+            item.text = subtitle::convert_traditional_to_simplified_chinese(&item.text);
+        }
+    }
+
+    for format in args.formats.iter() {
+        let path = output_dir.join(format!("{stem}.{}", format.extension()));
+        save_subtitles(&subtitles, *format, args.speaker_prefix, &path)?;
+        info!("Saved {}", path.display());
+    }
+
+    Ok(())
+}
+
+fn save_subtitles(
+    subtitles: &[Subtitle],
+    format: Format,
+    speaker_prefix: bool,
+    path: &Path,
+) -> Result<()> {
+    let Some(exporter) = subtitle::exporter_with_speaker_prefix(format.extension(), speaker_prefix)
+    else {
+        bail!("unsupported subtitle format {}", format.extension());
+    };
+
+    exporter.save(subtitles, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hotword() -> Result<()> {
+        assert_eq!(
+            parse_hotword("Rust")?,
+            ("Rust".to_string(), DEFAULT_HOTWORD_BIAS)
+        );
+        assert_eq!(parse_hotword(" Slint : 2.5 ")?, ("Slint".to_string(), 2.5));
+        assert_eq!(parse_hotword("a:b:1")?, ("a:b".to_string(), 1.0));
+        assert!(parse_hotword("Rust:high").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_media_files() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("whispercap-cli-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub.mp3"))?;
+        for name in ["b.MP4", "a.mp3", "notes.txt", "noext"] {
+            fs::write(dir.join(name), [])?;
+        }
+
+        let files = collect_media_files(&dir)?;
+        assert_eq!(files, vec![dir.join("a.mp3"), dir.join("b.MP4")]);
+        assert_eq!(
+            collect_media_files(&dir.join("notes.txt"))?,
+            vec![dir.join("notes.txt")]
+        );
+        assert!(collect_media_files(&dir.join("missing")).is_err());
+
+        fs::write(dir.join("a.wav"), [])?;
+        let duplicated = collect_media_files(&dir);
+
+        fs::remove_dir_all(&dir)?;
+        assert!(duplicated.is_err());
+        Ok(())
+    }
+}
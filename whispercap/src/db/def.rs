@@ -1,13 +1,17 @@
 use crate::slint_generatedAppWindow::{
-    MediaType as UIMediaType, ModelEntry as UIModelEntry, ModelSource, ModelStatus,
+    DetectedLang as UIDetectedLang, DiarizationMethod, MediaType as UIMediaType,
+    ModelEntry as UIModelEntry, ModelSource, ModelStatus, SamplingStrategy,
     SubtitleEntry as UISubtitleEntry, SubtitleSetting as UISubtitleSetting,
-    TextListEntry as UITextListEntry, TranscribeEntry as UITranscribeEntry,
+    SubtitleWord as UISubtitleWord, TextListEntry as UITextListEntry,
+    TranscribeAdvancedSetting as UITranscribeAdvancedSetting, TranscribeEntry as UITranscribeEntry,
+    WhisperSetting as UIWhisperSetting,
 };
 use ffmpeg::MediaType;
 use serde::de::{self, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use slint::{Model, ModelRc, VecModel};
 use std::fmt;
+use transcribe::whisper::TranscriptionWord;
 
 pub const TRANSCRIBE_TABLE: &str = "transcribe";
 pub const MODEL_TABLE: &str = "model";
@@ -24,6 +28,18 @@ pub struct SubtitleEntry {
     pub end_timestamp: String,
     pub original_text: String,
     pub translation_text: String,
+
+    #[serde(default)]
+    pub words: Vec<TranscriptionWord>,
+
+    #[serde(default)]
+    pub speaker: String,
+
+    #[serde(default)]
+    pub hallucination: String,
+
+    #[serde(default)]
+    pub confidence: Option<f32>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -34,6 +50,82 @@ pub struct SubtitleSetting {
     pub enable_background: bool,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WhisperSetting {
+    pub sampling_strategy: SamplingStrategy,
+    pub best_of: i32,
+    pub beam_size: i32,
+    pub patience: f32,
+}
+
+impl Default for WhisperSetting {
+    fn default() -> Self {
+        Self {
+            sampling_strategy: SamplingStrategy::Greedy,
+            best_of: 1,
+            beam_size: 5,
+            patience: -1.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DetectedLang {
+    pub lang: String,
+    pub probability: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TranscribeAdvancedSetting {
+    pub temperature: f32,
+    pub initial_prompt: String,
+    pub n_threads: i32,
+    pub translate: bool,
+    pub max_segment_length: i32,
+    pub split_on_word: bool,
+    pub entropy_threshold: f32,
+    pub logprob_threshold: f32,
+    pub no_speech_threshold: f32,
+    pub suppress_blank: bool,
+
+    #[serde(default)]
+    pub diarization: DiarizationMethod,
+
+    #[serde(default)]
+    pub max_speakers: i32,
+
+    #[serde(default)]
+    pub mixed_language: bool,
+
+    #[serde(default)]
+    pub mixed_languages: String,
+
+    #[serde(default)]
+    pub glossary: String,
+}
+
+impl Default for TranscribeAdvancedSetting {
+    fn default() -> Self {
+        Self {
+            temperature: 0.0,
+            initial_prompt: String::default(),
+            n_threads: 0,
+            translate: false,
+            max_segment_length: 0,
+            split_on_word: false,
+            entropy_threshold: 2.4,
+            logprob_threshold: -1.0,
+            no_speech_threshold: 0.6,
+            suppress_blank: true,
+            diarization: DiarizationMethod::None,
+            max_speakers: 0,
+            mixed_language: false,
+            mixed_languages: String::default(),
+            glossary: String::default(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct TranscribeEntry {
     pub id: String,
@@ -44,6 +136,15 @@ pub struct TranscribeEntry {
     pub sidebar_entry: TextListEntry,
     pub subtitle_entries: Vec<SubtitleEntry>,
     pub subtitle_setting: SubtitleSetting,
+
+    #[serde(default)]
+    pub whisper_setting: WhisperSetting,
+
+    #[serde(default)]
+    pub advanced_setting: TranscribeAdvancedSetting,
+
+    #[serde(default)]
+    pub detected_langs: Vec<DetectedLang>,
 }
 
 impl From<UITextListEntry> for TextListEntry {
@@ -72,6 +173,10 @@ impl From<UISubtitleEntry> for SubtitleEntry {
             end_timestamp: entry.end_timestamp.into(),
             original_text: entry.original_text.into(),
             translation_text: entry.translation_text.into(),
+            words: entry.words.iter().map(|item| item.into()).collect(),
+            speaker: entry.speaker.into(),
+            hallucination: entry.hallucination.into(),
+            confidence: Some(entry.confidence).filter(|confidence| *confidence > 0.0),
         }
     }
 }
@@ -83,11 +188,43 @@ impl From<SubtitleEntry> for UISubtitleEntry {
             end_timestamp: entry.end_timestamp.into(),
             original_text: entry.original_text.into(),
             translation_text: entry.translation_text.into(),
+            words: ModelRc::new(
+                entry
+                    .words
+                    .into_iter()
+                    .map(|item| item.into())
+                    .collect::<VecModel<_>>(),
+            ),
+            speaker: entry.speaker.into(),
+            hallucination: entry.hallucination.into(),
+            confidence: entry.confidence.unwrap_or_default(),
             ..Default::default()
         }
     }
 }
 
+impl From<UISubtitleWord> for TranscriptionWord {
+    fn from(word: UISubtitleWord) -> Self {
+        Self {
+            text: word.text.into(),
+            start_time: word.start_timestamp.max(0) as u64,
+            end_time: word.end_timestamp.max(0) as u64,
+            probability: word.probability,
+        }
+    }
+}
+
+impl From<TranscriptionWord> for UISubtitleWord {
+    fn from(word: TranscriptionWord) -> Self {
+        Self {
+            text: word.text.into(),
+            start_timestamp: word.start_time as i32,
+            end_timestamp: word.end_time as i32,
+            probability: word.probability,
+        }
+    }
+}
+
 impl From<UISubtitleSetting> for SubtitleSetting {
     fn from(entry: UISubtitleSetting) -> Self {
         Self {
@@ -110,6 +247,90 @@ impl From<SubtitleSetting> for UISubtitleSetting {
     }
 }
 
+impl From<UIWhisperSetting> for WhisperSetting {
+    fn from(entry: UIWhisperSetting) -> Self {
+        Self {
+            sampling_strategy: entry.sampling_strategy,
+            best_of: entry.best_of,
+            beam_size: entry.beam_size,
+            patience: entry.patience,
+        }
+    }
+}
+
+impl From<WhisperSetting> for UIWhisperSetting {
+    fn from(entry: WhisperSetting) -> Self {
+        Self {
+            sampling_strategy: entry.sampling_strategy,
+            best_of: entry.best_of,
+            beam_size: entry.beam_size,
+            patience: entry.patience,
+        }
+    }
+}
+
+impl From<UIDetectedLang> for DetectedLang {
+    fn from(entry: UIDetectedLang) -> Self {
+        Self {
+            lang: entry.lang.into(),
+            probability: entry.probability,
+        }
+    }
+}
+
+impl From<DetectedLang> for UIDetectedLang {
+    fn from(entry: DetectedLang) -> Self {
+        Self {
+            lang: entry.lang.into(),
+            probability: entry.probability,
+        }
+    }
+}
+
+impl From<UITranscribeAdvancedSetting> for TranscribeAdvancedSetting {
+    fn from(entry: UITranscribeAdvancedSetting) -> Self {
+        Self {
+            temperature: entry.temperature,
+            initial_prompt: entry.initial_prompt.into(),
+            n_threads: entry.n_threads,
+            translate: entry.translate,
+            max_segment_length: entry.max_segment_length,
+            split_on_word: entry.split_on_word,
+            entropy_threshold: entry.entropy_threshold,
+            logprob_threshold: entry.logprob_threshold,
+            no_speech_threshold: entry.no_speech_threshold,
+            suppress_blank: entry.suppress_blank,
+            diarization: entry.diarization,
+            max_speakers: entry.max_speakers,
+            mixed_language: entry.mixed_language,
+            mixed_languages: entry.mixed_languages.into(),
+            glossary: entry.glossary.into(),
+        }
+    }
+}
+
+impl From<TranscribeAdvancedSetting> for UITranscribeAdvancedSetting {
+    fn from(entry: TranscribeAdvancedSetting) -> Self {
+        Self {
+            temperature: entry.temperature,
+            initial_prompt: entry.initial_prompt.into(),
+            n_threads: entry.n_threads,
+            translate: entry.translate,
+            max_segment_length: entry.max_segment_length,
+            split_on_word: entry.split_on_word,
+            entropy_threshold: entry.entropy_threshold,
+            logprob_threshold: entry.logprob_threshold,
+            no_speech_threshold: entry.no_speech_threshold,
+            suppress_blank: entry.suppress_blank,
+            diarization: entry.diarization,
+            max_speakers: entry.max_speakers,
+            mixed_language: entry.mixed_language,
+            mixed_languages: entry.mixed_languages.into(),
+            glossary: entry.glossary.into(),
+        }
+    }
+}
+
 impl From<UITranscribeEntry> for TranscribeEntry {
     fn from(entry: UITranscribeEntry) -> Self {
         Self {
@@ -125,6 +346,13 @@ impl From<UITranscribeEntry> for TranscribeEntry {
                 .map(|item| item.into())
                 .collect::<Vec<_>>(),
             subtitle_setting: entry.subtitle_setting.into(),
+            whisper_setting: entry.whisper_setting.into(),
+            advanced_setting: entry.advanced_setting.into(),
+            detected_langs: entry
+                .detected_langs
+                .iter()
+                .map(|item| item.into())
+                .collect::<Vec<_>>(),
         }
     }
 }
@@ -146,6 +374,15 @@ impl From<TranscribeEntry> for UITranscribeEntry {
                     .collect::<VecModel<_>>(),
             ),
             subtitle_setting: entry.subtitle_setting.into(),
+            whisper_setting: entry.whisper_setting.into(),
+            advanced_setting: entry.advanced_setting.into(),
+            detected_langs: ModelRc::new(
+                entry
+                    .detected_langs
+                    .into_iter()
+                    .map(|item| item.into())
+                    .collect::<VecModel<_>>(),
+            ),
             ..Default::default()
         }
     }
@@ -304,3 +541,98 @@ impl<'de> Deserialize<'de> for ModelStatus {
         deserializer.deserialize_str(ModelStatusVisitor)
     }
 }
+
+impl Serialize for SamplingStrategy {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            SamplingStrategy::Greedy => serializer.serialize_str("Greedy"),
+            SamplingStrategy::BeamSearch => serializer.serialize_str("BeamSearch"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SamplingStrategy {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct SamplingStrategyVisitor;
+
+        impl<'de> Visitor<'de> for SamplingStrategyVisitor {
+            type Value = SamplingStrategy;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter
+                    .write_str("a string representing SamplingStrategy ('Greedy' or 'BeamSearch')")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<SamplingStrategy, E>
+            where
+                E: de::Error,
+            {
+                match value {
+                    "Greedy" => Ok(SamplingStrategy::Greedy),
+                    "BeamSearch" => Ok(SamplingStrategy::BeamSearch),
+                    _ => Err(E::custom(format!(
+                        "unknown SamplingStrategy variant: {}",
+                        value
+                    ))),
+                }
+            }
+        }
+
+        deserializer.deserialize_str(SamplingStrategyVisitor)
+    }
+}
+
+impl Serialize for DiarizationMethod {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            DiarizationMethod::None => serializer.serialize_str("None"),
+            DiarizationMethod::Tinydiarize => serializer.serialize_str("Tinydiarize"),
+            DiarizationMethod::Clustering => serializer.serialize_str("Clustering"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for DiarizationMethod {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct DiarizationMethodVisitor;
+
+        impl<'de> Visitor<'de> for DiarizationMethodVisitor {
+            type Value = DiarizationMethod;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str(
+                    "a string representing DiarizationMethod ('None', 'Tinydiarize' or 'Clustering')",
+                )
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<DiarizationMethod, E>
+            where
+                E: de::Error,
+            {
+                match value {
+                    "None" => Ok(DiarizationMethod::None),
+                    "Tinydiarize" => Ok(DiarizationMethod::Tinydiarize),
+                    "Clustering" => Ok(DiarizationMethod::Clustering),
+                    _ => Err(E::custom(format!(
+                        "unknown DiarizationMethod variant: {}",
+                        value
+                    ))),
+                }
+            }
+        }
+
+        deserializer.deserialize_str(DiarizationMethodVisitor)
+    }
+}
@@ -27,6 +27,12 @@ pub fn init(ui: &AppWindow) {
                 "remove-all-subtitles" => {
                     global_logic!(ui).invoke_remove_all_subtitles();
                 }
+                "import-subtitles" => {
+                    global_logic!(ui).invoke_import_subtitles();
+                }
+                "remove-hallucinations" => {
+                    global_logic!(ui).invoke_remove_hallucinations();
+                }
                 "remove-subtitle" => {
                     let index = user_data.parse::<i32>().unwrap_or_default();
                     global_logic!(ui).invoke_remove_subtitle(index);
@@ -23,7 +23,10 @@ use std::{
         Arc, Mutex,
     },
 };
-use transcribe::whisper_model_downloader::{self, ModelDownloader};
+use transcribe::{
+    model_cache,
+    whisper_model_downloader::{self, ModelDownloader},
+};
 use uuid::Uuid;
 
 static CANCEL_SIGS: Lazy<Mutex<HashMap<String, Arc<AtomicBool>>>> =
@@ -379,6 +382,7 @@ fn cancel_download_model(ui: &AppWindow, index: i32) {
 
 fn remove_model(ui: &AppWindow, index: i32) {
     let entry = store_model_entries!(ui).remove(index as usize);
+    model_cache::release_whisper_model(entry.file_path.as_str());
     toast_success!(ui, tr("remove model successfully"));
 
     delete_db_entry(ui, entry.id.into());
@@ -3,7 +3,7 @@ use crate::{
     logic::tr::tr,
     slint_generatedAppWindow::{AppWindow, ConfirmDialogSetting, PopupActionSetting},
 };
-use slint::{ComponentHandle, SharedString};
+use slint::{ComponentHandle, Model, SharedString};
 
 pub fn init(ui: &AppWindow) {
     let ui_handle = ui.as_weak();
@@ -73,12 +73,41 @@ pub fn init(ui: &AppWindow) {
                         SharedString::default(),
                     );
                 }
+                "import-subtitles" => {
+                    let entry = global_logic!(ui).invoke_current_transcribe_entry();
+                    if entry.subtitle_entries.row_count() == 0 {
+                        global_logic!(ui).invoke_import_subtitles();
+                    } else {
+                        ui.global::<ConfirmDialogSetting>().invoke_set(
+                            true,
+                            tr("Warning").into(),
+                            tr("Replace all subtitles with the imported subtitles or not?").into(),
+                            "import-subtitles".to_string().into(),
+                            SharedString::default(),
+                        );
+                    }
+                }
                 "optimize-subtitles-timestamp" => {
                     global_logic!(ui).invoke_optimize_subtitles_timestamp();
                 }
                 "recover-subtitles-timestamp" => {
                     global_logic!(ui).invoke_recover_subtitles_timestamp();
                 }
+                "detect-hallucinations" => {
+                    global_logic!(ui).invoke_detect_hallucinations();
+                }
+                "remove-hallucinations" => {
+                    ui.global::<ConfirmDialogSetting>().invoke_set(
+                        true,
+                        tr("Warning").into(),
+                        tr("Remove all flagged subtitles or not?").into(),
+                        "remove-hallucinations".to_string().into(),
+                        SharedString::default(),
+                    );
+                }
+                "show-retranscribe-subtitles-dialog" => {
+                    global_logic!(ui).invoke_show_retranscribe_subtitles_dialog(user_data);
+                }
 
                 // ============= subtitle entry ================ //
                 "split-subtitle" => {
@@ -338,6 +338,69 @@ fn cn() -> &'static HashMap<&'static str, &'static str> {
             ("Refresh successfully", "刷新成功"),
             ("refresh", "刷新"),
             ("split subtitle failed", "分割字幕失败"),
+            ("Sampling strategy", "采样策略"),
+            ("Greedy", "贪心"),
+            ("Beam search", "束搜索"),
+            ("Best of", "候选数量"),
+            ("Beam size", "束宽"),
+            ("Patience", "耐心系数"),
+            ("Resume from the last subtitle", "从最后一条字幕继续"),
+            ("Time range (optional)", "时间范围（可选）"),
+            ("The start timestamp should be less than the end timestamp", "开始时间戳应小于结束时间戳"),
+            ("Please select subtitles first", "请先选择字幕"),
+            ("Re-transcribe Setting", "重新转录设置"),
+            ("Initial prompt", "初始提示词"),
+            ("re-transcribe selected", "重新转录选中项"),
+            ("Re-transcribe", "重新转录"),
+            ("select", "选择"),
+            ("unselect", "取消选择"),
+            ("Advanced setting", "高级设置"),
+            ("Temperature", "温度"),
+            ("Threads (0 is auto)", "线程数（0 为自动）"),
+            ("Max segment length (0 is no limit)", "最大片段长度（0 为不限制）"),
+            ("Entropy threshold", "熵阈值"),
+            ("Logprob threshold", "对数概率阈值"),
+            ("No speech threshold", "无语音阈值"),
+            ("Translate into English", "翻译成英文"),
+            ("Split on word", "按单词切分"),
+            ("Suppress blank", "抑制空白"),
+            ("Speaker diarization", "说话人分离"),
+            ("None", "无"),
+            ("Voice clustering", "声音聚类"),
+            ("Tinydiarize (tdrz models only)", "Tinydiarize（仅支持 tdrz 模型）"),
+            ("Max speakers (0 is 2)", "最大说话人数（0 为 2）"),
+            ("Detected language", "检测到的语言"),
+            ("Mixed language (detect per chunk)", "混合语言（逐段检测）"),
+            ("Candidate languages (optional)", "候选语言（可选）"),
+            ("detect hallucinations", "检测幻觉字幕"),
+            ("remove hallucinations", "移除幻觉字幕"),
+            ("Remove all flagged subtitles or not?", "是否移除所有已标记的字幕?"),
+            ("Likely hallucination", "疑似幻觉"),
+            ("found likely hallucinated subtitles", "发现疑似幻觉字幕"),
+            ("repeated text", "重复文本"),
+            ("high compression ratio", "压缩率过高"),
+            ("low confidence", "置信度低"),
+            ("no speech", "无语音"),
+            ("next low-confidence subtitle", "下一个低置信度字幕"),
+            ("confidence", "置信度"),
+            ("low-confidence words", "低置信度词"),
+            ("no low-confidence subtitle", "没有低置信度字幕"),
+            ("Glossary (separated by commas or lines)", "术语表（用逗号或换行分隔）"),
+            ("import subtitles", "导入字幕"),
+            ("Import subtitles", "导入字幕"),
+            ("Choose a subtitle file", "选择字幕文件"),
+            ("import subtitles failed", "导入字幕失败"),
+            ("import subtitles successfully", "导入字幕成功"),
+            ("Replace all subtitles with the imported subtitles or not?", "是否用导入的字幕替换所有字幕?"),
+            ("Bilingual subtitle", "双语字幕"),
+            ("Original over translation", "原文在上，译文在下"),
+            ("Translation over original", "译文在上，原文在下"),
+            ("Original only", "仅原文"),
+            ("Translation only", "仅译文"),
+            ("Separate original and translation", "原文和译文分开"),
+            ("unsupported subtitle format", "不支持的字幕格式"),
+            ("Please install ffmpeg to convert", "请安装ffmpeg以转换"),
+            ("Prefix the subtitles with the speakers", "在字幕前加上说话人"),
         ])
     })
 }
@@ -2,7 +2,10 @@ use crate::{
     config,
     db::{
         self,
-        def::{TranscribeEntry, TRANSCRIBE_TABLE as DB_TABLE},
+        def::{
+            TranscribeAdvancedSetting, TranscribeEntry, WhisperSetting,
+            TRANSCRIBE_TABLE as DB_TABLE,
+        },
     },
     global_logic, global_store,
     logic::{
@@ -11,10 +14,14 @@ use crate::{
     },
     slint_generatedAppWindow::{
         AiHandleSubtitleSetting as UIAiHandleSubtitleSetting, AppWindow,
-        ExportVideoSetting as UIExportVideoSetting, MediaType as UIMediaType, PopupIndex,
-        ProgressType, SubtitleEntry as UISubtitleEntry, SubtitleSetting as UISubtitleSetting,
-        SystemFontInfo as UISystemFontInfo, TextListEntry as UITextListEntry,
+        BilingualMode as UIBilingualMode, DetectedLang as UIDetectedLang,
+        DiarizationMethod as UIDiarizationMethod, ExportVideoSetting as UIExportVideoSetting,
+        MediaType as UIMediaType, PopupIndex, ProgressType, SamplingStrategy as UISamplingStrategy,
+        SubtitleEntry as UISubtitleEntry, SubtitleSetting as UISubtitleSetting,
+        SubtitleWord as UISubtitleWord, SystemFontInfo as UISystemFontInfo,
+        TextListEntry as UITextListEntry, TranscribeAdvancedSetting as UITranscribeAdvancedSetting,
         TranscribeEntry as UITranscribeEntry, VideoPlayerSetting as UIVideoPlayerSetting,
+        WhisperSetting as UIWhisperSetting,
     },
     toast_info, toast_success, toast_warn,
 };
@@ -45,12 +52,22 @@ use std::{
 };
 use tokio::{sync::mpsc, task::AbortHandle};
 use transcribe::{
-    subtitle::{self, Subtitle},
+    diarization::{DiarizationMethod, DEFAULT_MAX_SPEAKERS},
+    hallucination::HallucinationDetector,
+    subtitle::{
+        self, AssConfig, BilingualMode, Exporter, SrtExporter, Subtitle, VttAlign, VttConfig,
+        VttStyle,
+    },
+    wav,
+    whisper::{self, SamplingStrategy, TranscriptionSegment, TranscriptionWord, WhisperConfig},
     whisper_lang::WhisperLang,
-    SegmentCallbackData,
 };
 use uuid::Uuid;
 
+const TRANSCRIBE_CHECKPOINT_SEGMENTS: usize = 10;
+const LOW_CONFIDENCE_THRESHOLD: f32 = 0.6; // of the subtitles and the words
+const RETRANSCRIBE_PADDING_MS: u64 = 200; // keep the words on the edges of the subtitles
+
 static MEDIA_INC_NUM: AtomicU64 = AtomicU64::new(0);
 static CACHE: Lazy<Mutex<Cache>> = Lazy::new(|| Mutex::new(Cache::default()));
 
@@ -217,7 +234,67 @@ pub fn init(ui: &AppWindow) {
         }
 
         global_logic!(ui).invoke_switch_popup(PopupIndex::None);
-        start_transcribe(&ui, entry);
+        start_transcribe(&ui, entry, TranscribeMode::Full);
+    });
+
+    let ui_weak = ui.as_weak();
+    global_logic!(ui).on_resume_transcribe(move |entry| {
+        let ui = ui_weak.unwrap();
+
+        if get_progressing() {
+            toast_warn!(ui, tr("Already runing whisper transcription"));
+            return;
+        }
+
+        global_logic!(ui).invoke_switch_popup(PopupIndex::None);
+        start_transcribe(&ui, entry, TranscribeMode::Resume);
+    });
+
+    let ui_weak = ui.as_weak();
+    global_logic!(ui).on_start_transcribe_range(move |entry, start_timestamp, end_timestamp| {
+        let ui = ui_weak.unwrap();
+
+        if get_progressing() {
+            toast_warn!(ui, tr("Already runing whisper transcription"));
+            return;
+        }
+
+        // the empty timestamps are the start and the end of the media
+        let start_ms = if start_timestamp.trim().is_empty() {
+            Ok(0)
+        } else {
+            transcribe::subtitle::srt_timestamp_to_ms(start_timestamp.trim())
+        };
+
+        let end_ms = if end_timestamp.trim().is_empty() {
+            Ok(u64::MAX)
+        } else {
+            transcribe::subtitle::srt_timestamp_to_ms(end_timestamp.trim())
+        };
+
+        let (Ok(start_ms), Ok(end_ms)) = (start_ms, end_ms) else {
+            toast_warn!(
+                ui,
+                format!(
+                    "{}. {} -> {}",
+                    tr("invalid timestamp"),
+                    start_timestamp,
+                    end_timestamp
+                )
+            );
+            return;
+        };
+
+        if start_ms >= end_ms {
+            toast_warn!(
+                ui,
+                tr("The start timestamp should be less than the end timestamp")
+            );
+            return;
+        }
+
+        global_logic!(ui).invoke_switch_popup(PopupIndex::None);
+        start_transcribe(&ui, entry, TranscribeMode::Range(start_ms, end_ms));
     });
 
     let ui_weak = ui.as_weak();
@@ -254,11 +331,20 @@ pub fn init(ui: &AppWindow) {
         import_media_file(&ui_weak.unwrap());
     });
 
+    // ffmpeg is required by all the files without the symphonia feature
+    global_logic!(ui).on_is_ffmpeg_required(move |file_path| {
+        if file_path.is_empty() {
+            return !cfg!(feature = "symphonia");
+        }
+
+        !whisper::is_converted_in_process(file_path.as_str())
+    });
+
     let ui_weak = ui.as_weak();
-    global_logic!(ui).on_export_subtitles(move |ty| {
+    global_logic!(ui).on_export_subtitles(move |ty, mode, speaker_prefix| {
         let ui = ui_weak.unwrap();
         global_logic!(ui).invoke_switch_popup(PopupIndex::None);
-        export_subtitles(&ui, ty.into());
+        export_subtitles(&ui, ty.into(), mode.into(), speaker_prefix);
     });
 
     let ui_weak = ui.as_weak();
@@ -283,6 +369,19 @@ pub fn init(ui: &AppWindow) {
             setting.chunk_size = 10;
         }
 
+        // the translation is useless if it's the same as the original subtitles
+        if ty.as_str() == "translate" {
+            let entry = global_logic!(ui).invoke_current_transcribe_entry();
+            let source_lang = entry.detected_langs.row_data(0).map(|item| item.lang);
+
+            let is_replaced =
+                setting.lang.is_empty() || source_lang.is_some_and(|lang| lang == setting.lang);
+
+            if let Some(lang) = default_translation_lang(&entry).filter(|_| is_replaced) {
+                setting.lang = lang.into();
+            }
+        }
+
         if setting.lang.is_empty() {
             setting.lang = "English".to_string().into();
         }
@@ -379,6 +478,26 @@ pub fn init(ui: &AppWindow) {
         recover_subtitles_timestamp(&ui_weak.unwrap());
     });
 
+    let ui_weak = ui.as_weak();
+    global_logic!(ui).on_import_subtitles(move || {
+        import_subtitles(&ui_weak.unwrap());
+    });
+
+    let ui_weak = ui.as_weak();
+    global_logic!(ui).on_detect_hallucinations(move || {
+        detect_hallucinations(&ui_weak.unwrap());
+    });
+
+    let ui_weak = ui.as_weak();
+    global_logic!(ui).on_remove_hallucinations(move || {
+        remove_hallucinations(&ui_weak.unwrap());
+    });
+
+    let ui_weak = ui.as_weak();
+    global_logic!(ui).on_jump_to_next_low_confidence_subtitle(move || {
+        jump_to_next_low_confidence_subtitle(&ui_weak.unwrap());
+    });
+
     let ui_weak = ui.as_weak();
     global_logic!(ui).on_split_subtitle(move |index| {
         split_subtitle(&ui_weak.unwrap(), index as usize);
@@ -419,6 +538,53 @@ pub fn init(ui: &AppWindow) {
         accept_subtitle_correction(&ui_weak.unwrap(), index as usize);
     });
 
+    let ui_weak = ui.as_weak();
+    global_logic!(ui).on_toggle_subtitle_selected(move |index| {
+        toggle_subtitle_selected(&ui_weak.unwrap(), index as usize);
+    });
+
+    let ui_weak = ui.as_weak();
+    global_logic!(ui).on_show_retranscribe_subtitles_dialog(move |index| {
+        let ui = ui_weak.unwrap();
+        let entry = global_logic!(ui).invoke_current_transcribe_entry();
+
+        // the action of a subtitle entry only re-transcribes itself
+        if let Ok(index) = index.parse::<usize>() {
+            for (row, mut subtitle) in store_transcribe_subtitle_entries!(entry).iter().enumerate()
+            {
+                if subtitle.is_selected != (row == index) {
+                    subtitle.is_selected = row == index;
+                    store_transcribe_subtitle_entries!(entry).set_row_data(row, subtitle);
+                }
+            }
+        }
+
+        if !store_transcribe_subtitle_entries!(entry)
+            .iter()
+            .any(|item| item.is_selected)
+        {
+            toast_warn!(ui, tr("Please select subtitles first"));
+            return;
+        }
+
+        global_logic!(ui).invoke_switch_popup(PopupIndex::RetranscribeSetting);
+    });
+
+    let ui_weak = ui.as_weak();
+    global_logic!(ui).on_retranscribe_selected_subtitles(
+        move |model_name, lang, initial_prompt| {
+            let ui = ui_weak.unwrap();
+
+            if get_progressing() {
+                toast_warn!(ui, tr("Already runing whisper transcription"));
+                return;
+            }
+
+            global_logic!(ui).invoke_switch_popup(PopupIndex::None);
+            retranscribe_selected_subtitles(&ui, model_name, lang, initial_prompt);
+        },
+    );
+
     global_logic!(ui)
         .on_is_valid_subtitle_timestamp(|timestamp| subtitle::valid_srt_timestamp(&timestamp));
 
@@ -524,6 +690,55 @@ pub fn init(ui: &AppWindow) {
         Some(handle) => handle.finished(),
     });
 
+    global_logic!(ui).on_hallucination_str(|reasons| {
+        reasons
+            .split(',')
+            .filter(|reason| !reason.is_empty())
+            .map(|reason| match reason {
+                "repetition" => tr("repeated text"),
+                "compression-ratio" => tr("high compression ratio"),
+                "low-confidence" => tr("low confidence"),
+                "no-speech" => tr("no speech"),
+                _ => reason.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+            .into()
+    });
+
+    global_logic!(ui).on_is_low_confidence_subtitle(|entry| is_low_confidence(&entry));
+
+    global_logic!(ui).on_confidence_str(|entry| {
+        let words = entry
+            .words
+            .iter()
+            .filter(|word| word.probability < LOW_CONFIDENCE_THRESHOLD)
+            .map(|word| word.text.trim().to_string())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>();
+
+        let confidence = format!("{}: {:.1}%", tr("confidence"), entry.confidence * 100.0);
+        if words.is_empty() {
+            confidence.into()
+        } else {
+            format!(
+                "{confidence}. {}: {}",
+                tr("low-confidence words"),
+                words.join(", ")
+            )
+            .into()
+        }
+    });
+
+    global_logic!(ui).on_detected_langs_str(|langs| {
+        langs
+            .iter()
+            .map(|item| format!("{} {:.1}%", tr(&item.lang), item.probability * 100.0))
+            .collect::<Vec<_>>()
+            .join(", ")
+            .into()
+    });
+
     global_logic!(ui).on_srt_timestamp_to_ms_second(|timestamp| {
         subtitle::srt_timestamp_to_ms(&timestamp).unwrap_or_default() as f32
     });
@@ -677,6 +892,9 @@ fn new_transcribe_entry(ui: &AppWindow) {
                 enable_background: false,
             };
 
+            entry.whisper_setting = WhisperSetting::default().into();
+            entry.advanced_setting = TranscribeAdvancedSetting::default().into();
+
             set_video_player_setting(
                 &ui,
                 &mut entry.video_player_setting,
@@ -807,6 +1025,11 @@ fn file_name(ui: Weak<AppWindow>, media_file: impl AsRef<Path>) -> Option<String
 }
 
 fn media_type(ui: Weak<AppWindow>, media_file: impl AsRef<Path>) -> Option<MediaType> {
+    // the audio files decoded in process are imported without ffprobe
+    if !ffmpeg::is_installed() && whisper::is_converted_in_process(&media_file) {
+        return Some(MediaType::Audio);
+    }
+
     match ffmpeg::media_type(&media_file) {
         Ok(ty) => {
             if ty == MediaType::Unknown {
@@ -971,6 +1194,7 @@ fn switch_sidebar_entry(ui: &AppWindow, old_index: i32, new_index: i32) {
         _ => (),
     }
 
+    global_store!(ui).set_focused_subtitle_index(-1);
     global_store!(ui).set_selected_transcribe_sidebar_index(new_index as i32);
 }
 
@@ -1125,7 +1349,14 @@ fn async_update_audio_player_setting_when_switch(
     }
 }
 
-fn start_transcribe(ui: &AppWindow, entry: UITranscribeEntry) {
+#[derive(Debug, Clone, Copy)]
+enum TranscribeMode {
+    Full,
+    Resume,
+    Range(u64, u64), // (start_ms, end_ms)
+}
+
+fn start_transcribe(ui: &AppWindow, entry: UITranscribeEntry, mode: TranscribeMode) {
     let ui_weak = ui.as_weak();
     let id = entry.id.to_string();
 
@@ -1143,8 +1374,51 @@ fn start_transcribe(ui: &AppWindow, entry: UITranscribeEntry) {
         return;
     };
 
+    let mut config = whisper_config(
+        &model_path,
+        lang,
+        &entry.whisper_setting,
+        &entry.advanced_setting,
+    );
+
+    let offset_ms = match mode {
+        TranscribeMode::Full => {
+            store_transcribe_subtitle_entries!(entry).set_vec(vec![]);
+            0
+        }
+        // continue from the end timestamp of the last subtitle
+        TranscribeMode::Resume => {
+            let last_end_timestamp = store_transcribe_subtitle_entries!(entry)
+                .iter()
+                .last()
+                .map(|item| transcribe::subtitle::srt_timestamp_to_ms(&item.end_timestamp));
+
+            match last_end_timestamp {
+                Some(Ok(ms)) => ms,
+                Some(Err(e)) => {
+                    toast_warn!(ui, format!("{}. {e}", tr("invalid timestamp")));
+                    return;
+                }
+                None => 0,
+            }
+        }
+        // only replace the subtitles which start inside the range
+        TranscribeMode::Range(start_ms, end_ms) => {
+            let subtitles = store_transcribe_subtitle_entries!(entry)
+                .iter()
+                .filter(|item| {
+                    transcribe::subtitle::srt_timestamp_to_ms(&item.start_timestamp)
+                        .map_or(true, |ms| ms < start_ms || ms >= end_ms)
+                })
+                .collect::<Vec<_>>();
+
+            store_transcribe_subtitle_entries!(entry).set_vec(subtitles);
+            config = config.with_time_range(start_ms, end_ms);
+            0
+        }
+    };
+
     let index = global_store!(ui).get_selected_transcribe_sidebar_index();
-    store_transcribe_subtitle_entries!(entry).set_vec(vec![]);
     store_transcribe_entries!(ui).set_row_data(index as usize, entry.clone());
     update_db_entry(ui, entry.into());
 
@@ -1166,13 +1440,89 @@ fn start_transcribe(ui: &AppWindow, entry: UITranscribeEntry) {
         }
 
         if !progress_cancelled() {
-            transcribe(ui_weak, id, &model_path, &output_audio_path, lang).await;
+            transcribe(ui_weak, id, config, &output_audio_path, offset_ms).await;
         }
 
         set_progressing(false);
     });
 }
 
+fn whisper_config(
+    model_path: &PathBuf,
+    lang: String,
+    setting: &UIWhisperSetting,
+    advanced_setting: &UITranscribeAdvancedSetting,
+) -> WhisperConfig {
+    let strategy = match setting.sampling_strategy {
+        UISamplingStrategy::Greedy => SamplingStrategy::Greedy {
+            best_of: setting.best_of.max(1),
+        },
+        UISamplingStrategy::BeamSearch => SamplingStrategy::BeamSearch {
+            beam_size: setting.beam_size.max(1),
+            patience: setting.patience,
+        },
+    };
+
+    let mut config = WhisperConfig::new(model_path)
+        .with_language(lang)
+        .with_sampling_strategy(strategy)
+        .with_temperature(advanced_setting.temperature)
+        .with_translate(advanced_setting.translate)
+        .with_entropy_threshold(advanced_setting.entropy_threshold)
+        .with_logprob_threshold(advanced_setting.logprob_threshold)
+        .with_no_speech_threshold(advanced_setting.no_speech_threshold.clamp(0.0, 1.0))
+        .with_suppress_blank(advanced_setting.suppress_blank);
+
+    if advanced_setting.n_threads > 0 {
+        config = config.with_threads(advanced_setting.n_threads);
+    }
+
+    if advanced_setting.max_segment_length > 0 {
+        config = config
+            .with_max_segment_length(advanced_setting.max_segment_length as u32)
+            .with_split_on_word(advanced_setting.split_on_word);
+    }
+
+    if !advanced_setting.initial_prompt.trim().is_empty() {
+        config = config.with_initial_prompt(advanced_setting.initial_prompt.trim());
+    }
+
+    config = config.with_glossary(
+        advanced_setting
+            .glossary
+            .split([',', '\n'])
+            .map(|term| term.trim())
+            .filter(|term| !term.is_empty()),
+    );
+
+    let max_speakers = match advanced_setting.max_speakers {
+        n if n > 0 => n as usize,
+        _ => DEFAULT_MAX_SPEAKERS,
+    };
+
+    match advanced_setting.diarization {
+        UIDiarizationMethod::Tinydiarize => {
+            config = config.with_diarization(DiarizationMethod::Tinydiarize, max_speakers);
+        }
+        UIDiarizationMethod::Clustering => {
+            config = config.with_diarization(DiarizationMethod::Clustering, max_speakers);
+        }
+        UIDiarizationMethod::None => (),
+    }
+
+    if advanced_setting.mixed_language {
+        config = config.with_mixed_language(
+            advanced_setting
+                .mixed_languages
+                .split([',', ' '])
+                .map(|lang| lang.trim())
+                .filter(|lang| !lang.is_empty()),
+        );
+    }
+
+    config
+}
+
 fn velify_transcribe_files(
     ui: &AppWindow,
     entry: &UITranscribeEntry,
@@ -1234,6 +1584,18 @@ fn convert_to_whisper_compatible_audio(
 ) -> bool {
     debug!("Convert to whisper compatible audio file...");
 
+    if !ffmpeg::is_installed() && !whisper::is_converted_in_process(input_media_path) {
+        toast::async_toast_warn(
+            ui_weak,
+            format!(
+                "{} {}",
+                tr("Please install ffmpeg to convert"),
+                input_media_path.display()
+            ),
+        );
+        return false;
+    }
+
     let (ui, id_duplicate) = (ui_weak.clone(), id.clone());
     _ = slint::invoke_from_event_loop(move || {
         let ui = ui.unwrap();
@@ -1260,6 +1622,13 @@ fn convert_to_whisper_compatible_audio(
             });
         },
     ) {
+        Err(_) if progress_cancelled() => {
+            toast::async_toast_info(
+                ui_weak.clone(),
+                tr("Cancelled converting to whisper compatible audio"),
+            );
+            return false;
+        }
         Err(e) => {
             toast::async_toast_warn(ui_weak.clone(), e.to_string());
             return false;
@@ -1294,11 +1663,11 @@ fn convert_to_whisper_compatible_audio(
 async fn transcribe(
     ui_weak: Weak<AppWindow>,
     id: String,
-    model_path: &PathBuf,
+    config: WhisperConfig,
     audio_path: &PathBuf,
-    lang: String,
+    offset_ms: u64,
 ) {
-    debug!("start transcribe. lang: {lang}");
+    debug!("start transcribe. offset: {offset_ms}ms, config: {config:?}");
 
     let (ui, id_duplicate) = (ui_weak.clone(), id.clone());
     _ = slint::invoke_from_event_loop(move || {
@@ -1306,47 +1675,73 @@ async fn transcribe(
         update_progress(&ui, id_duplicate, Some(ProgressType::Transcribe), 0.0);
     });
 
-    let config = transcribe::whisper::WhisperConfig::new(model_path).with_language(lang);
+    let ui_progress = ui_weak.clone();
+    let progress_cb = move |v: i32| {
+        debug!("whisper transcribe progress: {v}");
 
-    let (ui_progress, ui_segement) = (ui_weak.clone(), ui_weak.clone());
-    match transcribe::whisper::transcribe_file(
-        config,
-        &audio_path,
-        move |v: i32| {
-            debug!("whisper transcribe progress: {v}");
+        let ui = ui_progress.clone();
+        _ = slint::invoke_from_event_loop(move || {
+            let ui = ui.unwrap();
+            let id = global_logic!(ui)
+                .invoke_current_transcribe_entry()
+                .id
+                .into();
+            update_progress(&ui, id, None, v as f32 / 100.0);
+        });
+    };
 
-            let ui = ui_progress.clone();
-            _ = slint::invoke_from_event_loop(move || {
-                let ui = ui.unwrap();
-                let id = global_logic!(ui)
-                    .invoke_current_transcribe_entry()
-                    .id
-                    .into();
-                update_progress(&ui, id, None, v as f32 / 100.0);
-            });
-        },
-        move |segment: SegmentCallbackData| {
-            let ui = ui_segement.clone();
-            let segment: Subtitle = segment.into();
+    // the segments carry the words, speakers and confidence, so the
+    // saved rows are complete even if the transcription is interrupted
+    let (ui_segement, mut segment_count) = (ui_weak.clone(), 0);
+    let segment_cb = move |segment: TranscriptionSegment| {
+        let ui = ui_segement.clone();
+        let segment = Subtitle::from(&segment);
 
-            _ = slint::invoke_from_event_loop(move || {
-                let ui = ui.unwrap();
-                let entry = global_logic!(ui).invoke_current_transcribe_entry();
+        // save the subtitles regularly, so the transcription could be resumed after a crash
+        segment_count += 1;
+        let is_checkpoint = segment_count % TRANSCRIBE_CHECKPOINT_SEGMENTS == 0;
 
-                #[cfg(debug_assertions)]
-                {
-                    let contents = transcribe::subtitle::subtitle_to_srt(&segment);
-                    println!("{contents}\n");
-                }
+        _ = slint::invoke_from_event_loop(move || {
+            let ui = ui.unwrap();
+            let entry = global_logic!(ui).invoke_current_transcribe_entry();
 
-                store_transcribe_subtitle_entries!(entry).push(segment.into());
-            });
-        },
-        || progress_cancelled(),
-    )
-    .await
-    {
-        Ok(_) => {
+            #[cfg(debug_assertions)]
+            {
+                let contents = transcribe::subtitle::subtitle_to_srt(&segment);
+                println!("{contents}\n");
+            }
+
+            insert_subtitle_in_order(&entry, segment.into());
+
+            if is_checkpoint {
+                update_db_entry(&ui, entry.into());
+            }
+        });
+    };
+
+    let result = if offset_ms > 0 {
+        transcribe::whisper::resume_transcribe_file(
+            config,
+            &audio_path,
+            offset_ms,
+            progress_cb,
+            segment_cb,
+            progress_cancelled,
+        )
+        .await
+    } else {
+        transcribe::whisper::transcribe_file(
+            config,
+            &audio_path,
+            progress_cb,
+            segment_cb,
+            progress_cancelled,
+        )
+        .await
+    };
+
+    match result {
+        Ok(result) => {
             let (ui, id_duplicate) = (ui_weak.clone(), id.clone());
             _ = slint::invoke_from_event_loop(move || {
                 let ui = ui.unwrap();
@@ -1357,7 +1752,11 @@ async fn transcribe(
                     1.0,
                 );
 
-                let entry = global_logic!(ui).invoke_current_transcribe_entry();
+                let mut entry = global_logic!(ui).invoke_current_transcribe_entry();
+
+                entry.detected_langs = to_ui_detected_langs(&result.language_probabilities);
+                let index = global_store!(ui).get_selected_transcribe_sidebar_index();
+                store_transcribe_entries!(ui).set_row_data(index as usize, entry.clone());
                 update_db_entry(&ui, entry.into());
             });
         }
@@ -1367,10 +1766,86 @@ async fn transcribe(
             } else {
                 toast::async_toast_info(ui_weak.clone(), tr("Cancelled transcribing"));
             }
+
+            // keep the finished subtitles to resume later
+            let ui = ui_weak.clone();
+            _ = slint::invoke_from_event_loop(move || {
+                let ui = ui.unwrap();
+                let entry = global_logic!(ui).invoke_current_transcribe_entry();
+                update_db_entry(&ui, entry.into());
+            });
         }
     }
 }
 
+// The new subtitle is appended in most cases, so search from the end
+fn insert_subtitle_in_order(entry: &UITranscribeEntry, subtitle: UISubtitleEntry) {
+    let subtitles = store_transcribe_subtitle_entries!(entry);
+    let start_ms = transcribe::subtitle::srt_timestamp_to_ms(&subtitle.start_timestamp).ok();
+
+    let mut index = subtitles.row_count();
+    while index > 0 {
+        let prev_start_ms = subtitles
+            .row_data(index - 1)
+            .and_then(|item| transcribe::subtitle::srt_timestamp_to_ms(&item.start_timestamp).ok());
+
+        if prev_start_ms <= start_ms {
+            break;
+        }
+        index -= 1;
+    }
+
+    subtitles.insert(index, subtitle);
+}
+
+// the language codes are converted into the long names which the ui uses
+fn to_ui_detected_langs(langs: &[(String, f32)]) -> ModelRc<UIDetectedLang> {
+    ModelRc::new(
+        langs
+            .iter()
+            .filter_map(|(code, probability)| {
+                WhisperLang::get_long_name_from_code(code).map(|lang| UIDetectedLang {
+                    lang: lang.into(),
+                    probability: *probability,
+                })
+            })
+            .collect::<VecModel<UIDetectedLang>>(),
+    )
+}
+
+// Translate into the language of the ui, unless the subtitles are already in it
+fn default_translation_lang(entry: &UITranscribeEntry) -> Option<&'static str> {
+    let source_lang = match entry.detected_langs.row_data(0) {
+        Some(item) => item.lang.to_string(),
+        None => entry.lang.to_string(),
+    };
+
+    if WhisperLang::from_long_name(&source_lang).is_none_or(|lang| lang == WhisperLang::Auto) {
+        return None;
+    }
+
+    let ui_lang = if config::preference().language == "cn" {
+        "Chinese"
+    } else {
+        "English"
+    };
+
+    match (source_lang.as_str(), ui_lang) {
+        ("Chinese", "Chinese") => Some("English"),
+        ("English", "English") => Some("Chinese"),
+        _ => Some(ui_lang),
+    }
+}
+
+fn to_ui_words(words: &[TranscriptionWord]) -> ModelRc<UISubtitleWord> {
+    ModelRc::new(
+        words
+            .iter()
+            .map(|item| item.clone().into())
+            .collect::<VecModel<UISubtitleWord>>(),
+    )
+}
+
 fn cancel_progress(ui: &AppWindow, id: SharedString, ty: ProgressType) {
     set_progress_cancel_signal(true);
 
@@ -1423,28 +1898,104 @@ fn import_media_file(ui: &AppWindow) {
     });
 }
 
-fn export_subtitles(ui: &AppWindow, ty: String) {
+fn import_subtitles(ui: &AppWindow) {
+    let id = global_logic!(ui).invoke_current_transcribe_entry().id;
+    let ui = ui.as_weak();
+
+    tokio::spawn(async move {
+        let Some(subtitle_file) = picker_file(ui.clone(), &tr("Choose a subtitle file")) else {
+            return;
+        };
+
+        debug!("import {}", subtitle_file.display());
+
+        let subtitles = match transcribe::subtitle::load_subtitle_file(&subtitle_file) {
+            Ok(subtitles) => subtitles,
+            Err(e) => {
+                toast::async_toast_warn(ui, format!("{}. {}", tr("import subtitles failed"), e));
+                return;
+            }
+        };
+
+        _ = slint::invoke_from_event_loop(move || {
+            let ui = ui.unwrap();
+            let entry = global_logic!(ui).invoke_current_transcribe_entry();
+            if entry.id != id {
+                return;
+            }
+
+            store_transcribe_subtitle_entries!(entry).set_vec(
+                subtitles
+                    .into_iter()
+                    .map(UISubtitleEntry::from)
+                    .collect::<Vec<_>>(),
+            );
+            global_store!(ui).set_focused_subtitle_index(-1);
+
+            toast_success!(ui, tr("import subtitles successfully"));
+            update_db_entry(&ui, entry.into());
+        });
+    });
+}
+
+fn export_subtitles(ui: &AppWindow, ty: String, mode: BilingualMode, speaker_prefix: bool) {
     let entry = global_logic!(ui).invoke_current_transcribe_entry();
-    let mut filename = cutil::fs::file_name_without_ext(&entry.file_path);
-    filename.push_str(&format!(".{ty}"));
+    let stem = cutil::fs::file_name_without_ext(&entry.file_path);
+    let filename = format!("{stem}.{ty}");
 
     let Some(items) = to_subtitles(ui) else {
         return;
     };
 
+    // the cues look the same as the ones in the video player
+    let vtt_config = VttConfig::default()
+        .with_align(VttAlign::Center)
+        .with_style(VttStyle {
+            font_name: entry.subtitle_setting.font_name.to_string(),
+            font_size: entry.subtitle_setting.font_size.max(0) as u32,
+            is_white_font_color: entry.subtitle_setting.is_white_font_color,
+            enable_background: entry.subtitle_setting.enable_background,
+        });
+
+    let ass_config = AssConfig::default()
+        .with_font_name(&entry.subtitle_setting.font_name)
+        .with_font_size(entry.subtitle_setting.font_size.max(0) as u32)
+        .with_is_white_font_color(entry.subtitle_setting.is_white_font_color)
+        .with_enable_background(entry.subtitle_setting.enable_background);
+
+    let exporter: Box<dyn Exporter> = match ty.as_str() {
+        "vtt" => Box::new(vtt_config),
+        "ass" => Box::new(ass_config),
+        _ => match subtitle::exporter_with_speaker_prefix(&ty, speaker_prefix) {
+            Some(exporter) => exporter,
+            None => {
+                toast_warn!(ui, format!("{}: {ty}", tr("unsupported subtitle format")));
+                return;
+            }
+        },
+    };
+
     let ui = ui.as_weak();
     tokio::spawn(async move {
         let Some(path) = picker_directory(ui.clone(), &tr("Export Subtitle"), &filename) else {
             return;
         };
 
-        let path = path.join(filename);
-        let ret = match ty.as_str() {
-            "srt" => subtitle::save_as_srt(&items, path),
-            "vtt" => subtitle::save_as_vtt(&items, path),
-            "txt" => subtitle::save_as_txt(&items, path),
-            _ => unreachable!("Unsupport subtitle type"),
-        };
+        // the translation track is saved as `{stem}.translation.{ty}`
+        let mut ret = Ok(());
+        for (index, items) in mode.tracks(&items).iter().enumerate() {
+            let path = if index == 0 {
+                path.join(&filename)
+            } else {
+                path.join(format!("{stem}.translation.{ty}"))
+            };
+
+            ret = exporter.save(items, &path);
+
+            if ret.is_err() {
+                break;
+            }
+        }
 
         match ret {
             Err(e) => toast::async_toast_warn(ui, format!("{}. {e}", "save subtitle failed")),
@@ -1458,12 +2009,37 @@ fn export_video(ui: &AppWindow, setting: UIExportVideoSetting) {
         return;
     };
 
+    // two languages can't be burned into the video separately
+    let mode = match BilingualMode::from(setting.bilingual_mode) {
+        BilingualMode::Separate if setting.is_embedded => BilingualMode::OriginalOverTranslation,
+        mode => mode,
+    };
+
+    let tracks = mode.tracks(&subtitles);
     let subtitle_save_path = config::cache_dir().join(format!("{}.srt", setting.id));
-    if let Err(e) = subtitle::save_as_srt(&subtitles, &subtitle_save_path) {
+    let translation_save_path = config::cache_dir().join(format!("{}.translation.srt", setting.id));
+
+    // the speakers are burned into the video if they are prefixed
+    let exporter = SrtExporter {
+        speaker_prefix: setting.speaker_prefix,
+    };
+
+    if let Err(e) = exporter.save(&tracks[0], &subtitle_save_path) {
         toast_warn!(ui, format!("{}. {e}", tr("save subtitle failed.")));
         return;
     }
 
+    let translation_save_path = match tracks.get(1) {
+        Some(track) => {
+            if let Err(e) = exporter.save(track, &translation_save_path) {
+                toast_warn!(ui, format!("{}. {e}", tr("save subtitle failed.")));
+                return;
+            }
+            Some(translation_save_path)
+        }
+        None => None,
+    };
+
     let ui_weak = ui.as_weak();
     tokio::spawn(async move {
         let filename = cutil::fs::file_name(&setting.file_path);
@@ -1495,6 +2071,7 @@ fn export_video(ui: &AppWindow, setting: UIExportVideoSetting) {
                 ui_weak.clone(),
                 &setting,
                 &subtitle_save_path,
+                translation_save_path.as_ref(),
                 &add_subtitle_input_path,
                 &add_subtitle_output_path,
             );
@@ -1579,16 +2156,21 @@ fn add_subtitle(
     ui_weak: Weak<AppWindow>,
     setting: &UIExportVideoSetting,
     subtitle_save_path: &PathBuf,
+    translation_save_path: Option<&PathBuf>,
     input_path: &PathBuf,
     output_path: &PathBuf,
 ) -> bool {
-    let config = SubtitleConfig::new(subtitle_save_path)
+    let mut config = SubtitleConfig::new(subtitle_save_path)
         .with_font_name(&setting.inner.font_name)
         .with_font_size((setting.inner.font_size as u32).max(1))
         .with_is_white_font_color(setting.inner.is_white_font_color)
         .with_enable_background(setting.inner.enable_background)
         .with_is_embedded(setting.is_embedded);
 
+    if let Some(path) = translation_save_path {
+        config = config.with_translation_path(path);
+    }
+
     let (ui, id) = (ui_weak.clone(), setting.id.clone().to_string());
     _ = slint::invoke_from_event_loop(move || {
         update_progress(&ui.unwrap(), id, Some(ProgressType::AddSubtitle), 0.0);
@@ -2205,6 +2787,132 @@ fn recover_subtitles_timestamp(ui: &AppWindow) {
     update_db_entry(&ui, entry.into());
 }
 
+fn detect_hallucinations(ui: &AppWindow) {
+    let entry = global_logic!(ui).invoke_current_transcribe_entry();
+    let id = entry.id.clone().to_string();
+
+    let segments = store_transcribe_subtitle_entries!(entry)
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let words = item
+                .words
+                .iter()
+                .map(TranscriptionWord::from)
+                .collect::<Vec<_>>();
+            let confidence = match words.len() {
+                _ if item.confidence > 0.0 => item.confidence,
+                0 => 1.0,
+                n => words.iter().map(|word| word.probability).sum::<f32>() / n as f32,
+            };
+
+            TranscriptionSegment {
+                index: index as i32 + 1,
+                start_time: subtitle::srt_timestamp_to_ms(&item.start_timestamp).unwrap_or(0),
+                end_time: subtitle::srt_timestamp_to_ms(&item.end_timestamp).unwrap_or(0),
+                text: item.original_text.into(),
+                confidence,
+                words,
+                speaker: None,
+                language: None,
+                speaker_turn: false,
+                hallucination: vec![],
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if segments.is_empty() {
+        return;
+    }
+
+    let audio_path = config::cache_dir().join(format!("{id}.wav"));
+    let ui_weak = ui.as_weak();
+
+    tokio::spawn(async move {
+        // the text is still checked without the audio
+        let audio_data = match wav::read_file(&audio_path) {
+            Ok(audio_data) => Some(audio_data.to_mono()),
+            Err(e) => {
+                warn!("{e}");
+                None
+            }
+        };
+
+        let flags = HallucinationDetector::new().detect(
+            &segments,
+            audio_data
+                .as_ref()
+                .map(|audio_data| (audio_data.samples.as_slice(), audio_data.config.sample_rate)),
+        );
+
+        _ = slint::invoke_from_event_loop(move || {
+            let ui = ui_weak.unwrap();
+            let entry = global_logic!(ui).invoke_current_transcribe_entry();
+            let rows = store_transcribe_subtitle_entries!(entry);
+
+            // the subtitles have been changed in detecting
+            if entry.id != id || rows.row_count() != flags.len() {
+                return;
+            }
+
+            for (index, reasons) in flags.iter().enumerate() {
+                let mut item = rows.row_data(index).unwrap();
+                item.hallucination = reasons
+                    .iter()
+                    .map(|reason| reason.as_str())
+                    .collect::<Vec<_>>()
+                    .join(",")
+                    .into();
+                rows.set_row_data(index, item);
+            }
+
+            let count = flags.iter().filter(|reasons| !reasons.is_empty()).count();
+            toast_info!(
+                ui,
+                format!("{}: {count}", tr("found likely hallucinated subtitles"))
+            );
+
+            update_db_entry(&ui, entry.into());
+        });
+    });
+}
+
+// the unknown confidence is not low
+fn is_low_confidence(entry: &UISubtitleEntry) -> bool {
+    entry.confidence > 0.0 && entry.confidence < LOW_CONFIDENCE_THRESHOLD
+}
+
+fn jump_to_next_low_confidence_subtitle(ui: &AppWindow) {
+    let entry = global_logic!(ui).invoke_current_transcribe_entry();
+    let subtitles = store_transcribe_subtitle_entries!(entry);
+    let (subtitles_len, current_index) = (
+        subtitles.row_count(),
+        global_store!(ui).get_focused_subtitle_index(),
+    );
+
+    // search from the row after the focused one, and wrap around
+    let next_index = (1..=subtitles_len)
+        .map(|offset| (current_index.max(-1) + offset as i32) as usize % subtitles_len)
+        .find(|index| is_low_confidence(&subtitles.row_data(*index).unwrap()));
+
+    match next_index {
+        Some(index) => global_store!(ui).set_focused_subtitle_index(index as i32),
+        None => toast_info!(ui, tr("no low-confidence subtitle")),
+    }
+}
+
+fn remove_hallucinations(ui: &AppWindow) {
+    let entry = global_logic!(ui).invoke_current_transcribe_entry();
+    let subtitles = store_transcribe_subtitle_entries!(entry)
+        .iter()
+        .filter(|item| item.hallucination.is_empty())
+        .collect::<Vec<UISubtitleEntry>>();
+
+    store_transcribe_subtitle_entries!(entry).set_vec(subtitles);
+    toast_success!(ui, tr("remove subtitles successfully"));
+    update_db_entry(ui, entry.into());
+}
+
 fn split_subtitle(ui: &AppWindow, index: usize) {
     let entry = global_logic!(ui).invoke_current_transcribe_entry();
     let subtitles_len = store_transcribe_subtitle_entries!(entry).row_count();
@@ -2227,19 +2935,33 @@ fn split_subtitle(ui: &AppWindow, index: usize) {
         return;
     }
 
+    let words = subtitle
+        .words
+        .iter()
+        .map(|item| item.into())
+        .collect::<Vec<TranscriptionWord>>();
+
     let Some((first_part, second_part)) = transcribe::subtitle::split_subtitle_into_two(
         start_timestamp_ms.unwrap(),
         end_timestamp_ms.unwrap(),
         &subtitle.original_text,
+        &words,
     ) else {
         toast_warn!(ui, tr("split subtitle failed"));
         return;
     };
 
+    let (first_words, second_words): (Vec<_>, Vec<_>) = words
+        .into_iter()
+        .partition(|item| item.end_time <= first_part.1);
+
     let current_subtitle = UISubtitleEntry {
         start_timestamp: transcribe::subtitle::ms_to_srt_timestamp(first_part.0).into(),
         end_timestamp: transcribe::subtitle::ms_to_srt_timestamp(first_part.1).into(),
         original_text: first_part.2.into(),
+        words: to_ui_words(&first_words),
+        speaker: subtitle.speaker.clone(),
+        confidence: subtitle.confidence,
         ..Default::default()
     };
 
@@ -2247,6 +2969,9 @@ fn split_subtitle(ui: &AppWindow, index: usize) {
         start_timestamp: transcribe::subtitle::ms_to_srt_timestamp(second_part.0).into(),
         end_timestamp: transcribe::subtitle::ms_to_srt_timestamp(second_part.1).into(),
         original_text: second_part.2.into(),
+        words: to_ui_words(&second_words),
+        speaker: subtitle.speaker.clone(),
+        confidence: subtitle.confidence,
         ..Default::default()
     };
 
@@ -2300,6 +3025,20 @@ fn merge_above_subtitle(ui: &AppWindow, index: usize) {
         .translation_text
         .push_str(&current_subtitle.translation_text);
 
+    let words = prev_subtitle
+        .words
+        .iter()
+        .chain(current_subtitle.words.iter())
+        .map(|item| item.into())
+        .collect::<Vec<TranscriptionWord>>();
+    prev_subtitle.words = to_ui_words(&words);
+
+    // the merged subtitle is as confident as the less confident one
+    prev_subtitle.confidence = match (prev_subtitle.confidence, current_subtitle.confidence) {
+        (prev, current) if prev > 0.0 && current > 0.0 => prev.min(current),
+        (prev, current) => prev.max(current),
+    };
+
     store_transcribe_subtitle_entries!(entry).set_row_data(index - 1, prev_subtitle);
     store_transcribe_subtitle_entries!(entry).remove(index);
 
@@ -2400,7 +3139,7 @@ fn remove_subtitle(ui: &AppWindow, index: usize) {
     update_db_entry(&ui, entry.into());
 }
 
-fn save_subtitle(ui: &AppWindow, index: usize, subtitle: UISubtitleEntry) {
+fn save_subtitle(ui: &AppWindow, index: usize, mut subtitle: UISubtitleEntry) {
     let entry = global_logic!(ui).invoke_current_transcribe_entry();
     let subtitles_len = store_transcribe_subtitle_entries!(entry).row_count();
 
@@ -2408,6 +3147,8 @@ fn save_subtitle(ui: &AppWindow, index: usize, subtitle: UISubtitleEntry) {
         return;
     }
 
+    // the subtitle has been reviewed
+    subtitle.hallucination = SharedString::default();
     store_transcribe_subtitle_entries!(entry).set_row_data(index, subtitle);
     toast_success!(ui, tr("save subtitle successfully"));
 
@@ -2448,6 +3189,208 @@ fn accept_subtitle_correction(ui: &AppWindow, index: usize) {
     update_db_entry(&ui, entry.into());
 }
 
+fn toggle_subtitle_selected(ui: &AppWindow, index: usize) {
+    let entry = global_logic!(ui).invoke_current_transcribe_entry();
+
+    if let Some(mut subtitle) = store_transcribe_subtitle_entries!(entry).row_data(index) {
+        subtitle.is_selected = !subtitle.is_selected;
+        store_transcribe_subtitle_entries!(entry).set_row_data(index, subtitle);
+    }
+}
+
+fn retranscribe_selected_subtitles(
+    ui: &AppWindow,
+    model_name: SharedString,
+    lang: SharedString,
+    initial_prompt: SharedString,
+) {
+    let ui_weak = ui.as_weak();
+    let mut entry = global_logic!(ui).invoke_current_transcribe_entry();
+    let id = entry.id.to_string();
+
+    let mut subtitles = vec![];
+    for (index, item) in store_transcribe_subtitle_entries!(entry).iter().enumerate() {
+        if !item.is_selected {
+            continue;
+        }
+
+        let (Ok(start_ms), Ok(end_ms)) = (
+            subtitle::srt_timestamp_to_ms(&item.start_timestamp),
+            subtitle::srt_timestamp_to_ms(&item.end_timestamp),
+        ) else {
+            toast_warn!(
+                ui,
+                format!(
+                    "{}. {} -> {}",
+                    tr("invalid timestamp"),
+                    item.start_timestamp,
+                    item.end_timestamp
+                )
+            );
+            return;
+        };
+
+        if start_ms < end_ms {
+            subtitles.push((index, start_ms, end_ms));
+        }
+    }
+
+    if subtitles.is_empty() {
+        toast_warn!(ui, tr("Please select subtitles first"));
+        return;
+    }
+
+    let Some(lang) = WhisperLang::get_code_from_long_name(&lang) else {
+        toast_warn!(
+            ui,
+            format!("{}: {}", tr("Unsupport whisper language"), lang)
+        );
+        return;
+    };
+
+    // the model is only used this time, so it's not saved into the entry
+    entry.model_name = model_name;
+    let Some((model_path, input_media_path, output_audio_path, output_audio_path_tmp)) =
+        velify_transcribe_files(ui, &entry)
+    else {
+        return;
+    };
+
+    let mut config = whisper_config(
+        &model_path,
+        lang,
+        &entry.whisper_setting,
+        &entry.advanced_setting,
+    );
+    if !initial_prompt.trim().is_empty() {
+        config = config.with_initial_prompt(initial_prompt.trim());
+    }
+
+    tokio::spawn(async move {
+        set_progressing(true);
+        set_progress_cancel_signal(false);
+
+        if !output_audio_path.exists()
+            && !convert_to_whisper_compatible_audio(
+                ui_weak.clone(),
+                id.clone(),
+                &input_media_path,
+                &output_audio_path,
+                &output_audio_path_tmp,
+            )
+        {
+            set_progressing(false);
+            return;
+        }
+
+        if !progress_cancelled() {
+            retranscribe_subtitles(ui_weak, id, config, &output_audio_path, subtitles).await;
+        }
+
+        set_progressing(false);
+    });
+}
+
+// Cut the audio of the subtitles from the cached wav file, and offer
+// the new text as the corrections which could be accepted or rejected
+async fn retranscribe_subtitles(
+    ui_weak: Weak<AppWindow>,
+    id: String,
+    config: WhisperConfig,
+    audio_path: &PathBuf,
+    subtitles: Vec<(usize, u64, u64)>, // (index, start_ms, end_ms)
+) {
+    debug!("start re-transcribe. config: {config:?}");
+
+    let (ui, id_duplicate) = (ui_weak.clone(), id.clone());
+    _ = slint::invoke_from_event_loop(move || {
+        let ui = ui.unwrap();
+        update_progress(&ui, id_duplicate, Some(ProgressType::Transcribe), 0.0);
+    });
+
+    let (transcriber, mut reader) = match (
+        transcribe::whisper::WhisperTranscriber::new(config),
+        transcribe::wav::WavChunkReader::open(audio_path),
+    ) {
+        (Ok(transcriber), Ok(reader)) => (transcriber, reader),
+        (Err(e), _) | (_, Err(e)) => {
+            toast::async_toast_warn(ui_weak.clone(), e.to_string());
+            return;
+        }
+    };
+
+    let sample_rate = reader.config().sample_rate as u64;
+    let frame_count = reader.frame_count();
+    let total = subtitles.len();
+
+    for (count, (index, start_ms, end_ms)) in subtitles.into_iter().enumerate() {
+        let start_frame =
+            (start_ms.saturating_sub(RETRANSCRIBE_PADDING_MS) * sample_rate / 1000) as usize;
+        let end_frame =
+            (((end_ms + RETRANSCRIBE_PADDING_MS) * sample_rate / 1000) as usize).min(frame_count);
+
+        let audio_data = reader
+            .seek(start_frame)
+            .and_then(|_| reader.read_frames(end_frame.saturating_sub(start_frame)));
+
+        let result = match audio_data {
+            Ok(audio_data) => {
+                transcriber
+                    .transcribe_audio_data(&audio_data, |_| {}, |_| {}, progress_cancelled)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+
+        // skip the failed subtitle, and keep on re-transcribing the others
+        let text = match result {
+            Ok(result) => result.text,
+            Err(_) if progress_cancelled() => {
+                toast::async_toast_info(ui_weak.clone(), tr("Cancelled transcribing"));
+                return;
+            }
+            Err(e) => {
+                warn!("re-transcribe subtitle {} failed: {e:?}", index + 1);
+                toast::async_toast_warn(ui_weak.clone(), e.to_string());
+                String::default()
+            }
+        };
+
+        let (ui, id) = (ui_weak.clone(), id.clone());
+        _ = slint::invoke_from_event_loop(move || {
+            let ui = ui.unwrap();
+            let entry = global_logic!(ui).invoke_current_transcribe_entry();
+
+            // the subtitles may be edited during the transcription
+            let subtitle = store_transcribe_subtitle_entries!(entry)
+                .row_data(index)
+                .filter(|item| {
+                    entry.id == id
+                        && subtitle::srt_timestamp_to_ms(&item.start_timestamp).ok()
+                            == Some(start_ms)
+                        && subtitle::srt_timestamp_to_ms(&item.end_timestamp).ok() == Some(end_ms)
+                });
+
+            if let Some(mut subtitle) = subtitle {
+                if !text.trim().is_empty() {
+                    subtitle.correction_text = text.trim().into();
+                }
+                subtitle.is_selected = false;
+                store_transcribe_subtitle_entries!(entry).set_row_data(index, subtitle);
+                update_db_entry(&ui, entry.into());
+            }
+
+            let progress = (count + 1) as f32 / total as f32;
+            let ty = if count + 1 == total {
+                Some(ProgressType::TranscribeFinished)
+            } else {
+                None
+            };
+            update_progress(&ui, id, ty, progress);
+        });
+    }
+}
+
 fn video_player_start(ui: &AppWindow, timestamp: f32, duration: Option<f32>) {
     let entry = global_logic!(ui).invoke_current_transcribe_entry();
     let path = entry.file_path.to_string();
@@ -2936,11 +3879,11 @@ fn to_subtitle(index: i32, entry: &UISubtitleEntry) -> Result<Subtitle> {
         index,
         start_timestamp: subtitle::srt_timestamp_to_ms(&entry.start_timestamp)?,
         end_timestamp: subtitle::srt_timestamp_to_ms(&entry.end_timestamp)?,
-        text: if entry.translation_text.is_empty() {
-            entry.original_text.to_string()
-        } else {
-            format!("{}\n{}", entry.original_text, entry.translation_text)
-        },
+        text: entry.original_text.to_string(),
+        translation: Some(entry.translation_text.to_string()).filter(|item| !item.is_empty()),
+        words: entry.words.iter().map(|item| item.into()).collect(),
+        speaker: Some(entry.speaker.trim().to_string()).filter(|item| !item.is_empty()),
+        confidence: Some(entry.confidence).filter(|item| *item > 0.0),
     })
 }
 
@@ -2982,12 +3925,28 @@ impl Default for Cache {
     }
 }
 
+impl From<UIBilingualMode> for BilingualMode {
+    fn from(mode: UIBilingualMode) -> Self {
+        match mode {
+            UIBilingualMode::OriginalOverTranslation => BilingualMode::OriginalOverTranslation,
+            UIBilingualMode::TranslationOverOriginal => BilingualMode::TranslationOverOriginal,
+            UIBilingualMode::OriginalOnly => BilingualMode::OriginalOnly,
+            UIBilingualMode::TranslationOnly => BilingualMode::TranslationOnly,
+            UIBilingualMode::Separate => BilingualMode::Separate,
+        }
+    }
+}
+
 impl From<Subtitle> for UISubtitleEntry {
     fn from(sub: Subtitle) -> Self {
         UISubtitleEntry {
             start_timestamp: transcribe::subtitle::ms_to_srt_timestamp(sub.start_timestamp).into(),
             end_timestamp: transcribe::subtitle::ms_to_srt_timestamp(sub.end_timestamp).into(),
             original_text: sub.text.into(),
+            translation_text: sub.translation.unwrap_or_default().into(),
+            words: to_ui_words(&sub.words),
+            speaker: sub.speaker.unwrap_or_default().into(),
+            confidence: sub.confidence.unwrap_or_default(),
             ..Default::default()
         }
     }